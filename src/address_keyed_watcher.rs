@@ -0,0 +1,29 @@
+use asr::{
+    watcher::{Pair, Watcher},
+    Address64,
+};
+
+/// A [`Watcher`] for a value that is read from an object at `addr`.
+/// The old value is forgotten once the address changes,
+/// so that it is not compared against the value of a different object.
+pub struct AddressKeyedWatcher<T> {
+    addr: Address64,
+    watcher: Watcher<T>,
+}
+
+impl<T: Copy> AddressKeyedWatcher<T> {
+    pub fn new(addr: Address64) -> Self {
+        Self {
+            addr,
+            watcher: Watcher::new(),
+        }
+    }
+
+    pub fn update(&mut self, addr: Address64, value: Option<T>) -> Option<&Pair<T>> {
+        if addr != self.addr {
+            self.addr = addr;
+            self.watcher = Watcher::new();
+        }
+        self.watcher.update(value)
+    }
+}
@@ -0,0 +1,28 @@
+//! The types that make up the public surface of the splitter.
+//!
+//! Items marked as **stable** only change together with a major version bump.
+//! Items marked as **unstable** are still being worked on and may change with any release.
+
+/// **Stable**: the splits that can be emitted.
+#[doc(inline)]
+pub use crate::progress::Split;
+
+/// **Stable**: the actions that are applied to the timer.
+#[doc(inline)]
+pub use crate::progress::Action;
+
+/// **Stable**: the user settings.
+#[doc(inline)]
+pub use crate::settings::Settings;
+
+/// **Unstable**: the driver of the state machine.
+#[doc(inline)]
+pub use crate::progress::Progress;
+
+/// **Unstable**: new game values are added as they are needed.
+#[doc(inline)]
+pub use crate::data::GameDataSource;
+
+/// **Unstable**: the states follow the route and change with it.
+#[doc(inline)]
+pub use crate::progress::SplitProgression;
@@ -0,0 +1,43 @@
+use arrayvec::ArrayVec;
+
+/// A map for a few entries without allocating, looking up keys in insertion order.
+pub struct ArrayMap<K, V, const N: usize> {
+    entries: ArrayVec<(K, V), N>,
+}
+
+impl<K: PartialEq, V, const N: usize> ArrayMap<K, V, N> {
+    pub fn new() -> Self {
+        Self {
+            entries: ArrayVec::new(),
+        }
+    }
+
+    /// Inserts or replaces the value for the key, returns false if the map is full.
+    pub fn insert(&mut self, key: K, value: V) -> bool {
+        if let Some(existing) = self.get_mut(&key) {
+            *existing = value;
+            return true;
+        }
+        self.entries.try_push((key, value)).is_ok()
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.entries.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.entries
+            .iter_mut()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v)
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let index = self.entries.iter().position(|(k, _)| k == key)?;
+        Some(self.entries.swap_remove(index).1)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.entries.iter().map(|(k, v)| (k, v))
+    }
+}
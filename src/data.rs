@@ -1,32 +1,413 @@
+use core::{marker::PhantomData, time::Duration};
+
+use arrayvec::{ArrayString, ArrayVec};
 use asr::{
-    game_engine::unity::il2cpp::{Class, Module, Version},
+    future::next_tick,
+    game_engine::unity::il2cpp::{Class, Image, Module, Version},
+    watcher::{Pair, Watcher},
     Address, Address64, Process,
 };
+use bytemuck::{CheckedBitPattern, Pod};
+
+use crate::ext::{PairExt, PairF64Ext};
+
+/// The game values that drive the splits, implemented by [`Data`] for the real game.
+pub trait GameDataSource {
+    fn play_time(&self) -> Option<u64>;
+    fn play_time_f64(&self) -> Option<f64>;
+    fn party_level(&self) -> Option<u32>;
+    fn game_time_duration(&self) -> Option<Duration>;
+    fn tutorial_completed(&self) -> Option<bool>;
+    fn total_death_count(&self) -> Option<u32>;
+    fn is_loading(&self) -> Option<bool>;
+    fn is_paused(&self) -> Option<bool>;
+    fn is_cutscene(&self) -> Option<bool>;
+    fn is_credits_playing(&self) -> Option<bool>;
+    fn party_level_change(&mut self) -> Option<Pair<u32>>;
+    fn encounter_size(&mut self) -> Option<Pair<u32>>;
+    fn in_encounter(&self) -> Option<bool>;
+    fn is_in_combat(&self) -> Option<bool>;
+    fn combat_phase(&self) -> Option<CombatPhase>;
+    fn is_encounter_new(&self) -> bool;
+    fn party_member_count(&self) -> Option<u32>;
+    fn is_party_full_hp(&self) -> Option<bool>;
+    fn encounter_done(&mut self) -> Option<Pair<bool>>;
+    fn is_encounter_done(&self) -> Option<bool>;
+    fn encounter_wave_count(&self) -> Option<u32>;
+    fn all_enemies_defeated(&self) -> Option<bool>;
+    fn total_hp_remaining(&self) -> Option<u32>;
+    fn encounter_hp_snapshot(&self) -> Option<EncounterHpSnapshot>;
+    fn current_abilities_count(&self) -> Option<u32>;
+    fn current_equipment_count(&self) -> Option<u32>;
+    fn item_count(&self) -> Option<u32>;
+    fn is_world_map_unlocked(&self) -> Option<bool>;
+    fn current_save_slot(&self) -> Option<u32>;
+    fn first_enemy_start_hp(&self) -> Option<(Address64, u32)>;
+    fn first_enemy_id(&self) -> Option<u32>;
+    fn first_enemy_name(&self) -> Option<ArrayString<64>>;
+    fn current_hp(&self, enemy: Address64) -> Option<u32>;
+    fn enemy_max_hp(&self, enemy: Address64) -> Option<u32>;
+    fn encounter_hp_phase(&self, enemy: Address64, thresholds: &[u32]) -> Option<u8>;
+    fn encounter_enemies(&self) -> Option<ArrayVec<Address64, 8>>;
+
+    /// Updates the watcher and checks whether the play time went back to 0.
+    /// Failed reads keep the previous value, the game is not readable during the load.
+    fn play_time_reset_detected(&self, watcher: &mut Watcher<f64>) -> bool {
+        self.play_time_f64()
+            .is_some_and(|current| watcher.update_infallible(current).dropped_to_near_zero())
+    }
+}
+
+/// The number of characters that fight in an encounter.
+const PARTY_SIZE: usize = 3;
 
 pub struct Data<'a> {
     process: &'a Process,
+    module: Module,
+    version: Version,
+    session_start_tick: u64,
     progression: Singleton<ProgressionManagerBinding>,
+    tutorial_completed: OptionalField<bool>,
+    total_deaths: OptionalField<u32>,
     level: Singleton<LevelManagerBinding>,
     char_stats: Singleton<CharacterStatsManagerBinding>,
     party_data: PartyDataBinding,
     combat: Singleton<CombatManagerBinding>,
+    in_combat: OptionalField<bool>,
+    cutscene: OptionalSingleton,
+    cutscene_playing: OptionalField<bool>,
+    time: OptionalSingleton,
+    time_paused: OptionalField<bool>,
+    credits: OptionalSingleton,
+    credits_playing: OptionalField<bool>,
+    abilities: OptionalSingleton,
+    learned_abilities: OptionalField<Address64>,
+    equipment: OptionalSingleton,
+    equipped_count: OptionalField<u32>,
+    items: OptionalSingleton,
+    item_count: OptionalField<u32>,
+    party: OptionalSingleton,
+    party_member_count: OptionalField<u32>,
+    party_members: OptionalField<Address64>,
+    world_map: OptionalSingleton,
+    world_map_unlocked: OptionalField<bool>,
+    save: OptionalSingleton,
+    save_slot_index: OptionalField<u32>,
     encounter: EncounterBinding,
+    wave_count: OptionalField<u32>,
     enemy_target: EnemyCombatTargetBinding,
+    enemy_max_hp: OptionalField<u32>,
     combat_target: CombatTargetBinding,
     enemy_actor: EnemyCombatActorBinding,
+    enemy_id: OptionalField<u32>,
     char_data: CharacterDataBinding,
+    char_max_hp: OptionalField<u32>,
+    char_name: OptionalField<Address64>,
+    encounter_size_watcher: Watcher<u32>,
+    party_level_watcher: Watcher<u32>,
+    encounter_done_watcher: Watcher<bool>,
+    encounter_addr_watcher: Watcher<u64>,
+    party_progress_watcher: Watcher<u64>,
 }
 
 impl Data<'_> {
-    pub fn play_time(&self) -> Option<u64> {
-        Some(self.progression.read(self.process)?.play_time as _)
+    pub fn character_max_hp_at(&self, index: usize) -> Option<u32> {
+        let member = self.party_member_address(index)?;
+        self.char_max_hp.read(self.process, member.into())
     }
 
-    pub fn is_loading(&self) -> Option<bool> {
-        Some(self.level.read(self.process)?.is_loading)
+    /// The outer `None` means that the party could not be read,
+    /// the inner `None` means that there is no member in that slot.
+    pub fn all_party_hp(&self) -> Option<[Option<u32>; PARTY_SIZE]> {
+        let member_count = self.party_member_count()? as usize;
+        Some(core::array::from_fn(|index| {
+            (index < member_count)
+                .then(|| self.party_member_hp(index))
+                .flatten()
+        }))
+    }
+
+    fn party_member_hp(&self, index: usize) -> Option<u32> {
+        Some(self.party_member_at(index)?.hp)
+    }
+
+    fn party_member_at(&self, index: usize) -> Option<CharacterData> {
+        let member = self.party_member_address(index)?;
+        self.char_data.read(self.process, member.into()).ok()
+    }
+
+    fn party_member_address(&self, index: usize) -> Option<Address64> {
+        let members = self.party.read(self.process, &self.party_members)?;
+        self.read_from_unity_list(members.into(), index)
+    }
+
+    /// The party data is reloaded from checkpoints. Since [`Self::party_level`] follows
+    /// the pointer on every read, the new data is picked up without rebinding.
+    fn watch_party_progress(&mut self) {
+        let address = self
+            .char_stats
+            .read(self.process)
+            .map(|stats| stats.party_progress.value());
+        let relocated = self
+            .party_progress_watcher
+            .update(address)
+            .is_some_and(|addr| addr.changed() && addr.current != 0);
+        if relocated {
+            log_at!(
+                Warn,
+                "partyProgressData relocated to 0x{:x}",
+                address.unwrap_or(0)
+            );
+        }
+    }
+
+    /// Needs to be called once every tick, the optional managers
+    /// might only be created after the splitter has attached.
+    pub fn update_optional_singletons(&mut self) {
+        self.cutscene.find_instance(self.process, &self.module);
+        self.save.find_instance(self.process, &self.module);
+        self.world_map.find_instance(self.process, &self.module);
+        self.items.find_instance(self.process, &self.module);
+        self.equipment.find_instance(self.process, &self.module);
+        self.time.find_instance(self.process, &self.module);
+        self.party.find_instance(self.process, &self.module);
+        self.abilities.find_instance(self.process, &self.module);
+        self.credits.find_instance(self.process, &self.module);
+    }
+
+    /// Needs to be called once every tick for [`Self::is_encounter_new`] to work.
+    pub fn update_encounter_address(&mut self) {
+        let address = self
+            .combat
+            .read(self.process)
+            .map(|combat| combat.encounter.value());
+        self.encounter_addr_watcher.update(address);
+        if self.is_encounter_new() {
+            log_at!(Debug, "Encounter has been replaced");
+        }
+    }
+
+    /// Unlike [`Self::encounter_size`], this does not update the watcher that the splits rely on.
+    pub fn read_encounter_size(&self) -> Option<u32> {
+        self.enemy_list_size(&self.safe_encounter()?)
+    }
+
+    fn enemy_list_size(&self, encounter: &Encounter) -> Option<u32> {
+        const LIST_SIZE: u64 = 0x18;
+
+        self.process.read(encounter.enemy_targets + LIST_SIZE).ok()
+    }
+
+    pub fn enemy_count_alive(&self) -> Option<u32> {
+        self.enemy_hps()?
+            .try_fold(0, |alive, hp| Some(alive + u32::from(hp? > 0)))
+    }
+
+    fn enemy_hps(&self) -> Option<impl Iterator<Item = Option<u32>> + '_> {
+        let encounter_size = self.read_encounter_size()?;
+        Some((0..encounter_size).map(|index| self.current_hp(self.encounter_enemy_at(index)?)))
+    }
+
+    pub fn singleton_addresses(&self) -> [(&'static str, Address); 4] {
+        [
+            ("ProgressionManager", self.progression.address()),
+            ("LevelManager", self.level.address()),
+            ("CharacterStatsManager", self.char_stats.address()),
+            ("CombatManager", self.combat.address()),
+        ]
+    }
+
+    /// Logs every implausible address, not only the first one.
+    pub fn pointer_sanity_check(&self) -> bool {
+        self.singleton_addresses()
+            .into_iter()
+            .fold(true, |all_plausible, (name, address)| {
+                let plausible = is_plausible_address(address.value());
+                if !plausible {
+                    log_at!(Warn, "Implausible address for {name}: {address}");
+                }
+                all_plausible && plausible
+            })
+    }
+
+    pub fn detected_version(&self) -> Version {
+        self.version
+    }
+
+    /// The ticks are counted by the caller, starting with 0 once `Data` has been created.
+    pub fn session_ticks(&self, current_tick: u64) -> u64 {
+        current_tick.saturating_sub(self.session_start_tick)
+    }
+
+    /// Reads a byte without going through a class binding, to help with finding new offsets.
+    /// Raw reads bypass all safety checks, the address is not checked to be plausible
+    /// and the value is not checked to belong to any field.
+    #[cfg(debug_assertions)]
+    pub fn read_byte_at(&self, address: Address, offset: u64) -> Option<u8> {
+        self.process.read(address + offset).ok()
+    }
+
+    /// Like [`Self::read_byte_at`], raw reads bypass all safety checks.
+    #[cfg(debug_assertions)]
+    pub fn read_u32_at(&self, address: Address, offset: u64) -> Option<u32> {
+        self.process.read(address + offset).ok()
+    }
+
+    /// The other classes have been waited for, so only the optional ones can be missing.
+    fn log_optional_bindings(&self) {
+        let bindings = [
+            (
+                "ProgressionManager.tutorialCompleted",
+                self.tutorial_completed.is_found(),
+            ),
+            (
+                "ProgressionManager.totalDeathCount",
+                self.total_deaths.is_found(),
+            ),
+            ("CombatManager.isInCombat", self.in_combat.is_found()),
+            (
+                "CutsceneManager.isPlaying",
+                self.cutscene_playing.is_found(),
+            ),
+            ("TimeManager.isPaused", self.time_paused.is_found()),
+            ("CreditsManager.isPlaying", self.credits_playing.is_found()),
+            (
+                "AbilityManager.learnedAbilities",
+                self.learned_abilities.is_found(),
+            ),
+            (
+                "EquipmentManager.equippedCount",
+                self.equipped_count.is_found(),
+            ),
+            ("ItemManager.itemCount", self.item_count.is_found()),
+            (
+                "PartyManager.memberCount",
+                self.party_member_count.is_found(),
+            ),
+            ("PartyManager.members", self.party_members.is_found()),
+            (
+                "WorldMapManager.isUnlocked",
+                self.world_map_unlocked.is_found(),
+            ),
+            (
+                "SaveManager.currentSlotIndex",
+                self.save_slot_index.is_found(),
+            ),
+            ("Encounter.waveCount", self.wave_count.is_found()),
+            ("EnemyCombatTarget.maxHP", self.enemy_max_hp.is_found()),
+            ("EnemyCombatActor.enemyId", self.enemy_id.is_found()),
+            ("CharacterData.maxHp", self.char_max_hp.is_found()),
+            ("CharacterData.name", self.char_name.is_found()),
+        ];
+        for (name, found) in bindings {
+            log_at!(Debug, "{name} found: {found}");
+        }
+    }
+
+    fn safe_encounter(&self) -> Option<Encounter> {
+        // `done` is already validated as a proper bool when reading the encounter
+        let encounter = self.current_encounter()?;
+        is_plausible_address(encounter.enemy_targets.value()).then_some(encounter)
+    }
+
+    fn current_encounter(&self) -> Option<Encounter> {
+        let combat = self.combat.read(self.process)?;
+        self.encounter
+            .read(self.process, combat.encounter.into())
+            .ok()
+    }
+
+    fn first_enemy(&self) -> Option<Address64> {
+        self.encounter_enemy_at(0)
+    }
+
+    fn enemy_actor_at(&self, index: usize) -> Option<EnemyCombatActor> {
+        let actor = self.enemy_actor_address_at(index)?;
+        self.enemy_actor.read(self.process, actor.into()).ok()
+    }
+
+    fn enemy_actor_address_at(&self, index: usize) -> Option<Address64> {
+        let enemy = self.encounter_enemy_at(index as u32)?;
+        let combat_target = self.combat_target.read(self.process, enemy.into()).ok()?;
+        Some(combat_target.owner)
+    }
+
+    fn encounter_enemy_at(&self, index: u32) -> Option<Address64> {
+        let current_encounter = self.safe_encounter()?;
+        self.read_from_unity_list(current_encounter.enemy_targets.into(), index as usize)
+    }
+
+    /// A `String` has its length after the object header, followed by the UTF-16 characters.
+    /// Names that are longer than the buffer are cut off.
+    fn read_utf16_string<const N: usize>(&self, string: Address) -> Option<ArrayString<N>> {
+        const LENGTH: u64 = 0x10;
+        const CHARS: u64 = 0x14;
+
+        let length = self.process.read::<u32>(string + LENGTH).ok()? as usize;
+        let mut chars = [0_u16; N];
+        let chars = &mut chars[..length.min(N)];
+        self.process.read_into_slice(string + CHARS, chars).ok()?;
+
+        let mut name = ArrayString::new();
+        for c in char::decode_utf16(chars.iter().copied()) {
+            if name.try_push(c.ok()?).is_err() {
+                break;
+            }
+        }
+        Some(name)
+    }
+
+    /// A `List` has its items array behind the object header.
+    /// The array elements start after the array header,
+    /// so the item at `index` is at `SKIP_ARRAY_HEADER + index * size_of::<T>()`.
+    fn read_from_unity_list<T: Pod>(&self, list_ptr: Address, index: usize) -> Option<T> {
+        const SKIP_OBEJCT_HEADER: u64 = 0x10;
+        const SKIP_ARRAY_HEADER: u64 = 0x20;
+
+        let stride = core::mem::size_of::<T>() as u64;
+        self.process
+            .read_pointer_path64::<T>(
+                list_ptr,
+                &[
+                    SKIP_OBEJCT_HEADER,
+                    SKIP_ARRAY_HEADER + index as u64 * stride,
+                ],
+            )
+            .ok()
+    }
+}
+
+/// Where the party is in a fight, with the values that belong to it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CombatPhase {
+    OutOfCombat,
+    /// The encounter with this many enemies has been set up, but the fight has not started
+    InitiatingCombat(u32),
+    InCombat {
+        size: u32,
+        done: bool,
+    },
+    /// The encounter is over, but has not been cleared yet
+    CombatEnded,
+}
+
+/// The HP of the enemies at the start of an encounter.
+#[derive(Debug)]
+pub struct EncounterHpSnapshot {
+    pub hps: ArrayVec<u32, 8>,
+    pub total: u32,
+}
+
+impl GameDataSource for Data<'_> {
+    fn play_time(&self) -> Option<u64> {
+        Some(self.play_time_f64()? as _)
+    }
+
+    fn play_time_f64(&self) -> Option<f64> {
+        Some(self.progression.read(self.process)?.play_time)
     }
 
-    pub fn party_level(&self) -> Option<u32> {
+    fn party_level(&self) -> Option<u32> {
         let stats = self.char_stats.read(self.process)?;
         let progress = self
             .party_data
@@ -35,32 +416,171 @@ impl Data<'_> {
         Some(progress.current_level)
     }
 
-    pub fn encounter_size(&self) -> Option<u32> {
-        const LIST_SIZE: u64 = 0x18;
+    /// The play time with its sub-second precision, unlike [`Self::play_time`].
+    fn game_time_duration(&self) -> Option<Duration> {
+        let secs = self.play_time_f64()?;
+        (secs.is_finite() && secs >= 0.0).then(|| Duration::from_secs_f64(secs))
+    }
 
-        let current_encounter = self.current_encounter()?;
-        self.process
-            .read(current_encounter.enemy_targets + LIST_SIZE)
+    fn tutorial_completed(&self) -> Option<bool> {
+        self.tutorial_completed
+            .read(self.process, self.progression.address())
+    }
+
+    fn total_death_count(&self) -> Option<u32> {
+        self.total_deaths
+            .read(self.process, self.progression.address())
+    }
+
+    fn is_loading(&self) -> Option<bool> {
+        Some(self.level.read(self.process)?.is_loading)
+    }
+
+    fn is_paused(&self) -> Option<bool> {
+        self.time.read(self.process, &self.time_paused)
+    }
+
+    fn is_cutscene(&self) -> Option<bool> {
+        self.cutscene.read(self.process, &self.cutscene_playing)
+    }
+
+    fn is_credits_playing(&self) -> Option<bool> {
+        self.credits.read(self.process, &self.credits_playing)
+    }
+
+    fn party_level_change(&mut self) -> Option<Pair<u32>> {
+        self.watch_party_progress();
+        let party_level = self.party_level();
+        self.party_level_watcher.update(party_level).copied()
+    }
+
+    fn encounter_size(&mut self) -> Option<Pair<u32>> {
+        let encounter_size = self.read_encounter_size();
+        self.encounter_size_watcher.update(encounter_size).copied()
+    }
+
+    fn in_encounter(&self) -> Option<bool> {
+        let combat = self.combat.read(self.process)?;
+        Some(!combat.encounter.is_null())
+    }
+
+    fn is_in_combat(&self) -> Option<bool> {
+        self.in_combat.read(self.process, self.combat.address())
+    }
+
+    /// Reads the combat manager and the encounter only once, so that the values fit together.
+    fn combat_phase(&self) -> Option<CombatPhase> {
+        let combat = self.combat.read(self.process)?;
+        let in_combat = self.is_in_combat()?;
+        let encounter = self
+            .encounter
+            .read(self.process, combat.encounter.into())
             .ok()
+            .filter(|encounter| is_plausible_address(encounter.enemy_targets.value()));
+
+        let phase = match encounter {
+            None if in_combat => CombatPhase::InitiatingCombat(0),
+            None => CombatPhase::OutOfCombat,
+            Some(encounter) => {
+                let size = self.enemy_list_size(&encounter).unwrap_or(0);
+                match (in_combat, encounter.done) {
+                    (true, done) => CombatPhase::InCombat { size, done },
+                    (false, true) => CombatPhase::CombatEnded,
+                    (false, false) => CombatPhase::InitiatingCombat(size),
+                }
+            }
+        };
+        Some(phase)
     }
 
-    pub fn encounter_done(&self) -> Option<bool> {
-        let current_encounter = self.current_encounter()?;
-        Some(current_encounter.done)
+    fn is_encounter_new(&self) -> bool {
+        self.encounter_addr_watcher
+            .pair
+            .is_some_and(|addr| addr.changed_from_nonzero_to_different())
     }
 
-    pub fn first_enemy_start_hp(&self) -> Option<(Address64, u32)> {
-        let first_enemy = self.first_enemy()?;
+    fn party_member_count(&self) -> Option<u32> {
+        self.party.read(self.process, &self.party_member_count)
+    }
 
-        let combat_target = self
-            .combat_target
-            .read(self.process, first_enemy.into())
-            .ok()?;
+    fn is_party_full_hp(&self) -> Option<bool> {
+        let member_count = self.party_member_count()? as usize;
+        self.all_party_hp()?
+            .into_iter()
+            .take(member_count)
+            .enumerate()
+            .try_fold(true, |full, (index, hp)| {
+                Some(full && hp? == self.character_max_hp_at(index)?)
+            })
+    }
 
-        let combat_actor = self
-            .enemy_actor
-            .read(self.process, combat_target.owner.into())
-            .ok()?;
+    fn encounter_done(&mut self) -> Option<Pair<bool>> {
+        let encounter_done = self.is_encounter_done();
+        self.encounter_done_watcher.update(encounter_done).copied()
+    }
+
+    /// Unlike [`Self::encounter_done`], this does not update the watcher that the splits rely on.
+    fn is_encounter_done(&self) -> Option<bool> {
+        Some(self.safe_encounter()?.done)
+    }
+
+    fn encounter_wave_count(&self) -> Option<u32> {
+        let encounter = self.combat.read(self.process)?.encounter;
+        self.wave_count.read(self.process, encounter.into())
+    }
+
+    fn all_enemies_defeated(&self) -> Option<bool> {
+        let encounter_size = self.read_encounter_size()?;
+        let alive = self.enemy_count_alive()?;
+        Some(encounter_size > 0 && alive == 0)
+    }
+
+    fn total_hp_remaining(&self) -> Option<u32> {
+        self.enemy_hps()?
+            .try_fold(0_u32, |total, hp| Some(total.saturating_add(hp?)))
+    }
+
+    fn encounter_hp_snapshot(&self) -> Option<EncounterHpSnapshot> {
+        let mut snapshot = EncounterHpSnapshot {
+            hps: ArrayVec::new(),
+            total: 0,
+        };
+        for hp in self.enemy_hps()? {
+            let hp = hp?;
+            if snapshot.hps.try_push(hp).is_err() {
+                break;
+            }
+            snapshot.total = snapshot.total.saturating_add(hp);
+        }
+        Some(snapshot)
+    }
+
+    fn current_abilities_count(&self) -> Option<u32> {
+        const LIST_SIZE: u64 = 0x18;
+
+        let learned_abilities = self.abilities.read(self.process, &self.learned_abilities)?;
+        self.process.read(learned_abilities + LIST_SIZE).ok()
+    }
+
+    fn current_equipment_count(&self) -> Option<u32> {
+        self.equipment.read(self.process, &self.equipped_count)
+    }
+
+    fn item_count(&self) -> Option<u32> {
+        self.items.read(self.process, &self.item_count)
+    }
+
+    fn is_world_map_unlocked(&self) -> Option<bool> {
+        self.world_map.read(self.process, &self.world_map_unlocked)
+    }
+
+    fn current_save_slot(&self) -> Option<u32> {
+        self.save.read(self.process, &self.save_slot_index)
+    }
+
+    fn first_enemy_start_hp(&self) -> Option<(Address64, u32)> {
+        let first_enemy = self.first_enemy()?;
+        let combat_actor = self.enemy_actor_at(0)?;
 
         let char_data = self
             .char_data
@@ -70,33 +590,46 @@ impl Data<'_> {
         Some((first_enemy, char_data.hp))
     }
 
-    pub fn current_hp(&self, enemy: Address64) -> Option<u32> {
-        let enemy_target = self.enemy_target.read(self.process, enemy.into()).ok()?;
-        Some(enemy_target.current_hp)
+    fn first_enemy_id(&self) -> Option<u32> {
+        let actor = self.enemy_actor_address_at(0)?;
+        self.enemy_id.read(self.process, actor.into())
     }
 
-    fn current_encounter(&self) -> Option<Encounter> {
-        let combat = self.combat.read(self.process)?;
-        self.encounter
-            .read(self.process, combat.encounter.into())
-            .ok()
+    fn first_enemy_name(&self) -> Option<ArrayString<64>> {
+        let combat_actor = self.enemy_actor_at(0)?;
+        let name = self
+            .char_name
+            .read(self.process, combat_actor.data.into())?;
+
+        self.read_utf16_string(name.into())
     }
 
-    fn first_enemy(&self) -> Option<Address64> {
-        const SKIP_OBEJCT_HEADER: u64 = 0x10;
-        const SKIP_ARRAY_HEADER: u64 = 0x20;
+    fn current_hp(&self, enemy: Address64) -> Option<u32> {
+        let enemy_target = self.enemy_target.read(self.process, enemy.into()).ok()?;
+        Some(enemy_target.current_hp)
+    }
 
-        let current_encounter = self.current_encounter()?;
+    fn enemy_max_hp(&self, enemy: Address64) -> Option<u32> {
+        self.enemy_max_hp.read(self.process, enemy.into())
+    }
 
-        let first_enemy = self
-            .process
-            .read_pointer_path64::<Address64>(
-                current_encounter.enemy_targets,
-                &[SKIP_OBEJCT_HEADER, SKIP_ARRAY_HEADER],
-            )
-            .ok()?;
+    /// The number of thresholds that the HP of the enemy has dropped to or below.
+    /// The thresholds are expected in descending order.
+    fn encounter_hp_phase(&self, enemy: Address64, thresholds: &[u32]) -> Option<u8> {
+        let hp = self.current_hp(enemy)?;
+        let phase = thresholds
+            .iter()
+            .filter(|&&threshold| hp <= threshold)
+            .count();
+        u8::try_from(phase).ok()
+    }
 
-        Some(first_enemy)
+    fn encounter_enemies(&self) -> Option<ArrayVec<Address64, 8>> {
+        let encounter_size = self.read_encounter_size()?;
+        (0..encounter_size)
+            .take(8)
+            .map(|index| self.encounter_enemy_at(index))
+            .collect()
     }
 }
 
@@ -162,18 +695,50 @@ struct CharacterData {
 
 impl<'a> Data<'a> {
     pub async fn new(process: &'a Process) -> Data<'a> {
-        let module = Module::wait_attach(process, Version::V2020).await;
-        let image = module.wait_get_default_image(process).await;
-        log!("Attached to the game");
+        Self::new_with_image_name(process, "Assembly-CSharp").await
+    }
+
+    pub async fn new_with_image_name(process: &'a Process, image_name: &str) -> Data<'a> {
+        let (module, version) = attach_module(process).await;
+        let image = module.wait_get_image(process, image_name).await;
+        log_at!(Debug, "Attached to the game using image {image_name}");
+
+        const BIND_RETRIES: usize = 5;
+        const BIND_RETRY_TICKS: usize = 100;
 
         macro_rules! bind {
+            (@verified $cls:ty) => {{
+                BindProgress::BindingClass(stringify!($cls)).report();
+                let mut binding = <$cls>::bind(process, &module, &image).await;
+                for attempt in 1..=BIND_RETRIES {
+                    let valid = binding
+                        .class()
+                        .get_name::<64>(process, &module)
+                        .is_ok_and(|name| !name.is_empty());
+                    if valid {
+                        break;
+                    }
+
+                    log_at!(Warn,
+                        concat!("Binding for class ", stringify!($cls), " looks invalid, retry {}/{}"),
+                        attempt,
+                        BIND_RETRIES,
+                    );
+                    for _ in 0..BIND_RETRY_TICKS {
+                        next_tick().await;
+                    }
+                    binding = <$cls>::bind(process, &module, &image).await;
+                }
+                binding
+            }};
             ($cls:ty) => {{
-                let binding = <$cls>::bind(process, &module, &image).await;
-                log!(concat!("Created binding for class ", stringify!($cls)));
+                let binding = bind!(@verified $cls);
+                log_at!(Debug, concat!("Created binding for class ", stringify!($cls)));
                 binding
             }};
             (singleton $cls:ty) => {{
-                let binding = <$cls>::bind(process, &module, &image).await;
+                let binding = bind!(@verified $cls);
+                BindProgress::FindingInstance(stringify!($cls)).report();
                 let address = binding
                     .class()
                     .wait_get_parent(process, &module)
@@ -181,24 +746,165 @@ impl<'a> Data<'a> {
                     .wait_get_static_instance(process, &module, "instance")
                     .await;
 
-                log!(concat!("found ", stringify!($cls), " at {}"), address);
+                log_at!(Debug, concat!("found ", stringify!($cls), " at {}"), address);
 
                 Singleton { binding, address }
             }};
         }
 
-        Self {
+        let progression = bind!(singleton ProgressionManager);
+        let level = bind!(singleton LevelManager);
+        let char_stats = bind!(singleton CharacterStatsManager);
+
+        let party_data = bind!(PartyData);
+        let combat = bind!(singleton CombatManager);
+        let encounter = bind!(Encounter);
+        let enemy_target = bind!(EnemyCombatTarget);
+        let combat_target = bind!(CombatTarget);
+        let char_data = bind!(CharacterData);
+        let enemy_actor = bind!(EnemyCombatActor);
+
+        // Looked up once all the other classes are there, without waiting for them
+        let cutscene = OptionalSingleton::find(process, &module, &image, "CutsceneManager");
+        let cutscene_playing = cutscene.field(process, &module, "isPlaying");
+        let tutorial_completed = OptionalField::find(
+            process,
+            &module,
+            Some(progression.binding.class()),
+            "tutorialCompleted",
+        );
+        let total_deaths = OptionalField::find(
+            process,
+            &module,
+            Some(progression.binding.class()),
+            "totalDeathCount",
+        );
+        let in_combat =
+            OptionalField::find(process, &module, Some(combat.binding.class()), "isInCombat");
+        let char_max_hp = OptionalField::find(process, &module, Some(char_data.class()), "maxHp");
+        let char_name = OptionalField::find(process, &module, Some(char_data.class()), "name");
+        let enemy_max_hp =
+            OptionalField::find(process, &module, Some(enemy_target.class()), "maxHP");
+        let enemy_id = OptionalField::find(process, &module, Some(enemy_actor.class()), "enemyId");
+        let wave_count =
+            OptionalField::find(process, &module, Some(encounter.class()), "waveCount");
+        let credits = OptionalSingleton::find(process, &module, &image, "CreditsManager");
+        let credits_playing = credits.field(process, &module, "isPlaying");
+        let abilities = OptionalSingleton::find(process, &module, &image, "AbilityManager");
+        let learned_abilities = abilities.field(process, &module, "learnedAbilities");
+        let party = OptionalSingleton::find(process, &module, &image, "PartyManager");
+        let party_member_count = party.field(process, &module, "memberCount");
+        let party_members = party.field(process, &module, "members");
+        let time = OptionalSingleton::find(process, &module, &image, "TimeManager");
+        let time_paused = time.field(process, &module, "isPaused");
+        let equipment = OptionalSingleton::find(process, &module, &image, "EquipmentManager");
+        let equipped_count = equipment.field(process, &module, "equippedCount");
+        let items = OptionalSingleton::find(process, &module, &image, "ItemManager");
+        let item_count = items.field(process, &module, "itemCount");
+        let world_map = OptionalSingleton::find(process, &module, &image, "WorldMapManager");
+        let world_map_unlocked = world_map.field(process, &module, "isUnlocked");
+        let save = OptionalSingleton::find(process, &module, &image, "SaveManager");
+        let save_slot_index = save.field(process, &module, "currentSlotIndex");
+
+        let data = Self {
             process,
-            progression: bind!(singleton ProgressionManager),
-            level: bind!(singleton LevelManager),
-            char_stats: bind!(singleton CharacterStatsManager),
-            party_data: bind!(PartyData),
-            combat: bind!(singleton CombatManager),
-            encounter: bind!(Encounter),
-            enemy_target: bind!(EnemyCombatTarget),
-            combat_target: bind!(CombatTarget),
-            char_data: bind!(CharacterData),
-            enemy_actor: bind!(EnemyCombatActor),
+            progression,
+            tutorial_completed,
+            total_deaths,
+            level,
+            char_stats,
+            party_data,
+            combat,
+            in_combat,
+            cutscene,
+            cutscene_playing,
+            time,
+            time_paused,
+            credits,
+            credits_playing,
+            abilities,
+            learned_abilities,
+            equipment,
+            equipped_count,
+            items,
+            item_count,
+            party,
+            party_member_count,
+            party_members,
+            world_map,
+            world_map_unlocked,
+            save,
+            save_slot_index,
+            encounter,
+            wave_count,
+            enemy_target,
+            enemy_max_hp,
+            combat_target,
+            char_data,
+            char_max_hp,
+            char_name,
+            enemy_actor,
+            enemy_id,
+            encounter_size_watcher: Watcher::new(),
+            party_level_watcher: Watcher::new(),
+            encounter_done_watcher: Watcher::new(),
+            encounter_addr_watcher: Watcher::new(),
+            party_progress_watcher: Watcher::new(),
+            module,
+            version,
+            session_start_tick: 0,
+        };
+
+        data.log_optional_bindings();
+        BindProgress::Done.report();
+
+        data
+    }
+}
+
+/// What the splitter is waiting for while it attaches to the game.
+#[derive(Debug)]
+enum BindProgress {
+    BindingClass(&'static str),
+    FindingInstance(&'static str),
+    Done,
+}
+
+impl BindProgress {
+    fn report(self) {
+        log_at!(Debug, "{self:?}");
+        match self {
+            Self::BindingClass(class) => {
+                crate::set_variable("binding", format_args!("Binding class {class}"))
+            }
+            Self::FindingInstance(class) => {
+                crate::set_variable("binding", format_args!("Finding {class} instance"))
+            }
+            Self::Done => crate::set_variable("binding", "Done"),
+        }
+    }
+}
+
+async fn attach_module(process: &Process) -> (Module, Version) {
+    // asr has no separate layout for 2021, so the 2022 one is tried last
+    const VERSIONS: [Version; 3] = [Version::V2020, Version::V2019, Version::V2022];
+    const ATTACH_TICKS: usize = 500;
+
+    // Every version fails while the module is not loaded yet,
+    // which must not be mistaken for a version mismatch
+    while process.get_module_address("GameAssembly.dll").is_err() {
+        next_tick().await;
+    }
+
+    loop {
+        for version in VERSIONS {
+            for _ in 0..ATTACH_TICKS {
+                if let Some(module) = Module::attach(process, version) {
+                    log_at!(Debug, "Attached to IL2CPP {version:?}");
+                    return (module, version);
+                }
+                next_tick().await;
+            }
         }
     }
 }
@@ -208,6 +914,96 @@ struct Singleton<T> {
     address: Address,
 }
 
+impl<T> Singleton<T> {
+    pub fn address(&self) -> Address {
+        self.address
+    }
+}
+
+/// The static instance of a class that is not known to exist in every version of the game.
+/// Unlike [`Singleton`], nothing here waits, a missing class only disables what needs it.
+struct OptionalSingleton {
+    name: &'static str,
+    class: Option<Class>,
+    address: Option<Address>,
+}
+
+impl OptionalSingleton {
+    fn find(process: &Process, module: &Module, image: &Image, name: &'static str) -> Self {
+        let class = image.get_class(process, module, name);
+        if class.is_none() {
+            log_at!(Warn, "Class {name} not found, it will be ignored");
+        }
+        Self {
+            name,
+            class,
+            address: None,
+        }
+    }
+
+    /// Looks for the instance until it has been found.
+    fn find_instance(&mut self, process: &Process, module: &Module) {
+        if self.address.is_some() {
+            return;
+        }
+        let Some(class) = &self.class else {
+            return;
+        };
+
+        self.address = class
+            .get_parent(process, module)
+            .and_then(|parent| parent.get_static_instance(process, module, "instance"))
+            .filter(|address| is_plausible_address(address.value()));
+        if let Some(address) = self.address {
+            log_at!(Debug, "found {} at {address}", self.name);
+        }
+    }
+
+    fn field<T>(&self, process: &Process, module: &Module, name: &str) -> OptionalField<T> {
+        OptionalField::find(process, module, self.class.as_ref(), name)
+    }
+
+    fn read<T: CheckedBitPattern>(&self, process: &Process, field: &OptionalField<T>) -> Option<T> {
+        field.read(process, self.address?)
+    }
+}
+
+/// The offset of a field that is not known to exist in every version of the game.
+struct OptionalField<T> {
+    offset: Option<u32>,
+    value: PhantomData<T>,
+}
+
+impl<T> OptionalField<T> {
+    fn find(process: &Process, module: &Module, class: Option<&Class>, name: &str) -> Self {
+        let offset = class.and_then(|class| class.get_field(process, module, name));
+        if class.is_some() && offset.is_none() {
+            log_at!(Warn, "Field {name} not found, it will be ignored");
+        }
+        Self {
+            offset,
+            value: PhantomData,
+        }
+    }
+
+    fn is_found(&self) -> bool {
+        self.offset.is_some()
+    }
+}
+
+impl<T: CheckedBitPattern> OptionalField<T> {
+    fn read(&self, process: &Process, instance: Address) -> Option<T> {
+        process.read(instance + u64::from(self.offset?)).ok()
+    }
+}
+
+fn is_plausible_address(address: u64) -> bool {
+    const MIN_ADDRESS: u64 = 0x1000;
+    const MAX_ADDRESS: u64 = 0x7FFF_FFFF_FFFF_FFFF;
+
+    (MIN_ADDRESS..=MAX_ADDRESS).contains(&address) && address.is_multiple_of(8)
+}
+
 macro_rules! impl_binding {
     ($($cls:ty),+ $(,)?) => {
         $(::paste::paste! {
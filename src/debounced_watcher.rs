@@ -0,0 +1,73 @@
+use asr::watcher::{Pair, Watcher};
+
+/// A [`Watcher`] that only registers a change once the new value
+/// has been seen for `threshold` consecutive ticks.
+/// It can be updated more than once per tick without counting the tick again.
+pub struct DebouncedWatcher<T> {
+    inner: Watcher<T>,
+    /// The new value and the tick at which it has been seen first
+    pending: Option<(T, u64)>,
+    threshold: u8,
+}
+
+impl<T: Copy + PartialEq> DebouncedWatcher<T> {
+    pub fn new(threshold: u8) -> Self {
+        Self {
+            inner: Watcher::new(),
+            pending: None,
+            threshold,
+        }
+    }
+
+    pub fn set_threshold(&mut self, threshold: u8) {
+        self.threshold = threshold;
+    }
+
+    pub fn update(&mut self, value: Option<T>, tick: u64) -> Option<&Pair<T>> {
+        let Some(value) = value else {
+            self.pending = None;
+            return self.inner.update(None);
+        };
+
+        let accepted = match self.inner.pair.as_ref().map(|pair| pair.current) {
+            Some(current) if current != value => {
+                let since = match self.pending {
+                    Some((pending, since)) if pending == value => since,
+                    _ => tick,
+                };
+                let ticks = tick.saturating_sub(since) + 1;
+                if ticks >= u64::from(self.threshold) {
+                    self.pending = None;
+                    value
+                } else {
+                    // keep reporting the old value until the new one is stable
+                    self.pending = Some((value, since));
+                    current
+                }
+            }
+            _ => {
+                self.pending = None;
+                value
+            }
+        };
+
+        self.inner.update(Some(accepted))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_ticks_instead_of_updates() {
+        let mut watcher = DebouncedWatcher::new(2);
+        watcher.update(Some(false), 1);
+
+        // Seen twice, but only on one tick
+        assert!(!watcher.update(Some(true), 2).unwrap().current);
+        assert!(!watcher.update(Some(true), 2).unwrap().current);
+
+        assert!(watcher.update(Some(true), 3).unwrap().changed_to(&true));
+    }
+}
@@ -0,0 +1,49 @@
+use asr::watcher::Pair;
+
+/// Helpers on top of the ones that [`Pair`] already provides, like `changed_to` and `changed_from`.
+pub trait PairExt {
+    fn increased(&self) -> bool;
+    fn decreased(&self) -> bool;
+    fn changed_from_nonzero_to_different(&self) -> bool;
+}
+
+impl<T: Ord + Default> PairExt for Pair<T> {
+    fn increased(&self) -> bool {
+        self.current > self.old
+    }
+
+    fn decreased(&self) -> bool {
+        self.current < self.old
+    }
+
+    /// Both values have to be set, a drop back to zero does not count.
+    fn changed_from_nonzero_to_different(&self) -> bool {
+        self.old != T::default() && self.current != T::default() && self.current != self.old
+    }
+}
+
+pub trait PairF64Ext {
+    fn dropped_to_near_zero(&self) -> bool;
+}
+
+impl PairF64Ext for Pair<f64> {
+    /// The value can already be a few ticks past 0 when it is seen for the first time.
+    fn dropped_to_near_zero(&self) -> bool {
+        const NEAR_ZERO: f64 = 1.0;
+
+        self.current < self.old && self.current < NEAR_ZERO
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changed_from_nonzero_to_different() {
+        assert!(Pair { old: 1, current: 2 }.changed_from_nonzero_to_different());
+        assert!(!Pair { old: 0, current: 2 }.changed_from_nonzero_to_different());
+        assert!(!Pair { old: 1, current: 0 }.changed_from_nonzero_to_different());
+        assert!(!Pair { old: 1, current: 1 }.changed_from_nonzero_to_different());
+    }
+}
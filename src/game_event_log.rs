@@ -0,0 +1,41 @@
+/// Things that happen during a run, but do not necessarily split.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GameEvent {
+    EncounterStarted,
+    LevelLoad,
+    LevelUp,
+    PartyMemberJoined,
+    Died,
+}
+
+/// A timeline of the last `N` events of a run, together with the tick at which they happened.
+pub struct GameEventLog<const N: usize> {
+    events: [(GameEvent, u64); N],
+    count: usize,
+}
+
+impl<const N: usize> GameEventLog<N> {
+    pub fn new() -> Self {
+        Self {
+            events: [(GameEvent::LevelLoad, 0); N],
+            count: 0,
+        }
+    }
+
+    /// Drops the oldest event once the log is full.
+    pub fn record(&mut self, event: GameEvent, tick: u64) {
+        if self.count == N {
+            self.events.rotate_left(1);
+            self.count -= 1;
+        }
+        self.events[self.count] = (event, tick);
+        self.count += 1;
+    }
+
+    pub fn dump(&self) {
+        log_at!(Info, "{:>8} | event", "tick");
+        for (event, tick) in &self.events[..self.count] {
+            log_at!(Info, "{tick:>8} | {event:?}");
+        }
+    }
+}
@@ -1,9 +1,15 @@
 #![no_std]
 
+#[cfg(test)]
+extern crate std;
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
 use crate::{
     data::Data,
-    progress::{Action, Progress},
-    settings::Settings,
+    panel::DebugPanel,
+    progress::{Action, CompletionRating, PersistentStats, Progress, Stats},
+    settings::{AutoStartCondition, Settings},
 };
 use asr::{
     future::next_tick,
@@ -12,26 +18,58 @@ use asr::{
 };
 use progress::Split;
 
-#[cfg(debug_assertions)]
-macro_rules! log {
-    ($($arg:tt)*) => {{
-        let mut buf = ::arrayvec::ArrayString::<1024>::new();
-        let _ = ::core::fmt::Write::write_fmt(
-            &mut buf,
-            ::core::format_args!($($arg)*),
-        );
-        ::asr::print_message(&buf);
+macro_rules! log_at {
+    ($level:ident, $($arg:tt)*) => {{
+        if $crate::LogLevel::$level.is_enabled() {
+            let mut buf = ::arrayvec::ArrayString::<1024>::new();
+            let _ = ::core::fmt::Write::write_fmt(
+                &mut buf,
+                ::core::format_args!($($arg)*),
+            );
+            ::asr::print_message(&buf);
+        }
     }};
 }
 
-#[cfg(not(debug_assertions))]
-macro_rules! log {
-    ($($arg:tt)*) => {};
+#[derive(Copy, Clone, Debug)]
+enum LogLevel {
+    /// Announces what the splitter did, only in debug builds
+    Info,
+    /// Traces the game values, only in debug builds and with verbose logging enabled
+    Debug,
+    Warn,
+    Error,
+}
+
+static VERBOSE_LOGGING: AtomicBool = AtomicBool::new(false);
+
+/// How long to wait before binding again after the managers could not be found.
+const REATTACH_DELAY_TICKS: usize = 300;
+
+impl LogLevel {
+    fn is_enabled(self) -> bool {
+        match self {
+            Self::Info => cfg!(debug_assertions),
+            Self::Debug => cfg!(debug_assertions) && VERBOSE_LOGGING.load(Ordering::Relaxed),
+            Self::Warn | Self::Error => true,
+        }
+    }
 }
 
+mod address_keyed_watcher;
+pub mod api;
+mod array_map;
 mod data;
+mod debounced_watcher;
+mod ext;
+mod game_event_log;
+mod panel;
 mod progress;
+mod run_summary;
 mod settings;
+mod state_machine_debugger;
+#[cfg(test)]
+mod test_utils;
 
 asr::async_main!(stable);
 asr::panic_handler!();
@@ -39,26 +77,136 @@ asr::panic_handler!();
 async fn main() {
     asr::set_tick_rate(60.0);
     let settings = Settings::register();
-    log!("Loaded settings: {settings:?}");
+    VERBOSE_LOGGING.store(settings.toggles.verbose_logging, Ordering::Relaxed);
+    log_at!(Debug, "Loaded settings: {settings:?}");
+
+    // Lives outside of the attach loop so that the totals survive game restarts
+    let mut persistent_stats = PersistentStats::new();
 
     loop {
-        let process = Process::wait_attach("SeaOfStars.exe").await;
+        let process = wait_attach_any(&["SeaOfStars.exe", "SeaOfStars"]).await;
         process
             .until_closes(async {
-                let data = Data::new(&process).await;
-                let mut progress = Progress::new();
+                let mut data = Data::new(&process).await;
+                log_at!(Debug, "Detected IL2CPP {:?}", data.detected_version());
+                for (name, address) in data.singleton_addresses() {
+                    log_at!(Debug, "{name} @ 0x{address:x}");
+                }
+                #[cfg(debug_assertions)]
+                if settings.toggles.enable_raw_reads {
+                    log_raw_fields(&data);
+                }
+
+                // The managers stay where they are for as long as the game runs,
+                // so only binding them again can fix an implausible address.
+                if !data.pointer_sanity_check() {
+                    log_at!(
+                        Error,
+                        "Managers are not where they should be, attaching again"
+                    );
+                    for _ in 0..REATTACH_DELAY_TICKS {
+                        next_tick().await;
+                    }
+                    return;
+                }
+
+                let mut progress: Progress = Progress::new();
+                let mut panel = DebugPanel::new();
+                let mut tick = 0_u64;
 
                 loop {
                     if matches!(timer::state(), TimerState::NotRunning | TimerState::Ended) {
-                        progress.reset();
+                        if let Some(old_state) = progress.reset() {
+                            log_at!(Info, "Reset from state: {}", old_state.name());
+                        }
+                    }
+
+                    if settings.auto_start == AutoStartCondition::Manual
+                        && timer::state() == TimerState::Running
+                        && !progress.is_running()
+                    {
+                        log_at!(Info, "Timer was started by the runner");
+                        progress.start_manually(&data, &settings);
                     }
 
-                    while let Some(action) = progress.act(&data) {
-                        log!("Possible action: {action:?}");
-                        if let Some(action) = settings.filter(action) {
-                            log!("Decided on an action: {action:?}");
-                            act(action);
+                    tick += 1;
+                    progress.tick();
+                    data.update_optional_singletons();
+                    data.update_encounter_address();
+                    loop {
+                        let (action, diag) = progress.act_diag(&mut data, &settings);
+                        let Some(action) = action else {
+                            log_at!(Debug, "{diag:?}");
+                            break;
+                        };
+
+                        log_at!(Debug, "Possible action: {action:?}");
+                        if let Some(action) =
+                            settings.filter_with_context(action, progress.current_state_name())
+                        {
+                            log_at!(Debug, "Decided on an action: {action:?}");
+                            let split = matches!(action, Action::Split(_));
+                            if settings.toggles.persist_stats
+                                && matches!(action, Action::ResetAndStart)
+                            {
+                                persistent_stats.count_run();
+                                export_persistent_stats(&persistent_stats);
+                            }
+                            act(action, &settings);
+                            if split {
+                                export_stats(progress.stats());
+                            }
+                            if timer::state() == TimerState::Ended {
+                                log_at!(
+                                    Info,
+                                    "Session duration: {} ticks",
+                                    data.session_ticks(tick)
+                                );
+                                set_variable(
+                                    "completion_pct",
+                                    CompletionRating::compute(
+                                        progress.fixed_split_count(),
+                                        &settings,
+                                    ),
+                                );
+                            }
+                        }
+                    }
+
+                    if settings.toggles.persist_stats
+                        && persistent_stats.update(progress.stats(), progress.encounter_count())
+                    {
+                        export_persistent_stats(&persistent_stats);
+                    }
+
+                    if settings.toggles.show_debug_panel {
+                        panel.update(&data, &progress);
+                    }
+
+                    if cfg!(debug_assertions) {
+                        if let Some(level_loads) = progress.level_loads_count() {
+                            set_variable("level_loads", level_loads);
+                        }
+                        set_variable("encounters", progress.encounter_count());
+                        match progress.next_expected_split() {
+                            Some(split) => set_variable("next_split", format_args!("{split:?}")),
+                            None => set_variable("next_split", "-"),
+                        }
+                        if let Some(duration) = progress.loading().last_load_duration() {
+                            set_variable("last_load_ticks", duration);
+                            set_variable(
+                                "recent_load_ticks",
+                                format_args!("{:?}", progress.loading().recent_load_durations()),
+                            );
                         }
+                        set_variable(
+                            "splits",
+                            format_args!(
+                                "{}/{}",
+                                progress.fixed_split_count(),
+                                progress.expected_total_splits(&settings)
+                            ),
+                        );
                     }
 
                     next_tick().await;
@@ -68,48 +216,161 @@ async fn main() {
     }
 }
 
-fn act(action: Action) {
+/// Like [`Process::wait_attach`], but tries each of the names in turn on every tick.
+async fn wait_attach_any(process_names: &[&str]) -> Process {
+    loop {
+        for name in process_names {
+            if let Some(process) = Process::attach(name) {
+                log_at!(Info, "Attached to {name}");
+                return process;
+            }
+        }
+        next_tick().await;
+    }
+}
+
+/// Shows the first field of each manager both as a byte and as a u32,
+/// since the type of a field is not known before it has been bound.
+#[cfg(debug_assertions)]
+fn log_raw_fields(data: &Data<'_>) {
+    // The fields of an object start after the class pointer and the monitor
+    const FIRST_FIELD: u64 = 0x10;
+
+    for (name, address) in data.singleton_addresses() {
+        log_at!(
+            Info,
+            "{name} + 0x{FIRST_FIELD:x}: byte {:?}, u32 {:?}",
+            data.read_byte_at(address, FIRST_FIELD),
+            data.read_u32_at(address, FIRST_FIELD)
+        );
+    }
+}
+
+fn set_variable(key: &str, value: impl ::core::fmt::Display) {
+    let mut buf = ::arrayvec::ArrayString::<64>::new();
+    let _ = ::core::fmt::Write::write_fmt(&mut buf, ::core::format_args!("{value}"));
+    timer::set_variable(key, &buf);
+}
+
+fn export_stats(stats: &Stats) {
+    set_variable("split_count", stats.split_count);
+    set_variable("state", stats.state);
+    set_variable("load_count", stats.load_count);
+}
+
+fn export_persistent_stats(stats: &PersistentStats) {
+    set_variable("total_runs", stats.total_runs);
+    set_variable("total_deaths", stats.total_deaths);
+    set_variable("total_encounters", stats.total_encounters);
+}
+
+fn act(action: Action, settings: &Settings) {
     match action {
         Action::ResetAndStart => {
-            log!("Starting new run");
-            if timer::state() == TimerState::Ended {
+            log_at!(Info, "Starting new run");
+            if timer::state() != TimerState::NotRunning {
                 timer::reset();
             }
             timer::start();
         }
         Action::Split(split) => match split {
             Split::Mountain => {
-                log!("Climbed down the mountain");
+                log_at!(Info, "Climbed down the mountain");
                 timer::split();
             }
             Split::Town => {
-                log!("Left town");
+                log_at!(Info, "Left town");
                 timer::split();
             }
             Split::Mob => {
-                log!("Bested a mob of fiends");
+                log_at!(Info, "Bested a mob of fiends");
                 timer::split();
             }
             Split::LevelUp => {
-                log!("Party leveled up");
+                log_at!(Info, "Party leveled up");
                 timer::split();
             }
             Split::Dungeon => {
-                log!("Encountering final boss");
+                log_at!(Info, "Encountering final boss");
                 timer::split();
             }
             Split::Boss => {
-                log!("Run is finished");
+                log_at!(Info, "Run is finished");
+                timer::split();
+            }
+            Split::Milestone(id) => {
+                log_at!(Info, "Reached milestone {id}");
+                timer::split();
+            }
+            Split::Credits => {
+                log_at!(Info, "Credits finished rolling");
+                timer::split();
+            }
+            Split::TutorialSkipped => {
+                log_at!(Info, "Skipped the tutorial");
+                timer::split();
+            }
+            Split::AbilityAcquired => {
+                log_at!(Info, "Learned enough abilities");
+                timer::split();
+            }
+            Split::Encounters(count) => {
+                log_at!(Info, "Started {count} encounters");
+                timer::split();
+            }
+            Split::PartyMemberJoined => {
+                log_at!(Info, "A character joined the party");
+                timer::split();
+            }
+            Split::Died => {
+                log_at!(Info, "Party died");
+                timer::split();
+            }
+            Split::BossLowHP => {
+                log_at!(Info, "Boss is low on HP");
+                timer::split();
+            }
+            Split::FullHeal => {
+                log_at!(Info, "Party is fully healed");
+                timer::split();
+            }
+            Split::ItemCollected => {
+                log_at!(Info, "Collected an item");
+                timer::split();
+            }
+            Split::WorldMap => {
+                log_at!(Info, "Unlocked the world map");
+                timer::split();
+            }
+            Split::AnyEncounterDone => {
+                log_at!(Info, "Finished an encounter");
+                timer::split();
+            }
+            Split::BossPhase(phase) => {
+                log_at!(Info, "Boss entered phase {phase}");
                 timer::split();
             }
         },
+        Action::Pause if settings.toggles.invert_pause_behavior => {
+            log_at!(Info, "Resume game time for the load");
+            timer::resume_game_time();
+        }
+        Action::Resume if settings.toggles.invert_pause_behavior => {
+            log_at!(Info, "Pause game time after the load");
+            timer::pause_game_time();
+        }
         Action::Pause => {
-            log!("Pause game time");
+            log_at!(Info, "Pause game time");
             timer::pause_game_time();
         }
         Action::Resume => {
-            log!("Resume game time");
+            log_at!(Info, "Resume game time");
             timer::resume_game_time();
         }
+        Action::SetGameTime(game_time) => {
+            if let Ok(game_time) = game_time.try_into() {
+                timer::set_game_time(game_time);
+            }
+        }
     }
 }
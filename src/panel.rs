@@ -0,0 +1,51 @@
+use core::fmt::{Display, Write};
+
+use arrayvec::ArrayString;
+use asr::timer;
+
+use crate::{
+    data::{Data, GameDataSource},
+    progress::Progress,
+};
+
+pub struct DebugPanel {
+    values: [ArrayString<32>; 8],
+}
+
+impl DebugPanel {
+    pub fn new() -> Self {
+        Self {
+            values: [ArrayString::new(); 8],
+        }
+    }
+
+    pub fn update(&mut self, data: &Data<'_>, progress: &Progress) {
+        let boss_hp = progress
+            .boss_enemy()
+            .and_then(|enemy| data.current_hp(enemy));
+
+        self.show(0, "play_time", data.play_time());
+        self.show(1, "loading", data.is_loading());
+        self.show(2, "party_level", data.party_level());
+        // The panel must not update the watchers that the splits rely on
+        self.show(3, "encounter_size", data.read_encounter_size());
+        self.show(4, "encounter_done", data.is_encounter_done());
+        self.show(5, "boss_hp", boss_hp);
+        self.show(6, "state_name", Some(progress.current_state_name()));
+        self.show(7, "leader_max_hp", data.character_max_hp_at(0));
+    }
+
+    fn show(&mut self, slot: usize, key: &str, value: Option<impl Display>) {
+        let mut buf = ArrayString::new();
+        let _ = match value {
+            Some(value) => write!(buf, "{value}"),
+            None => buf.write_str("-"),
+        };
+
+        // only talk to the runtime when the value actually changed
+        if self.values[slot] != buf {
+            self.values[slot] = buf;
+            timer::set_variable(key, &buf);
+        }
+    }
+}
@@ -1,8 +1,24 @@
-use asr::{watcher::Watcher, Address64};
+use core::time::Duration;
 
-use crate::data::Data;
+use arrayvec::ArrayVec;
+use asr::{
+    watcher::{Pair, Watcher},
+    Address64,
+};
 
-#[derive(Debug)]
+use crate::{
+    address_keyed_watcher::AddressKeyedWatcher,
+    array_map::ArrayMap,
+    data::{CombatPhase, EncounterHpSnapshot, GameDataSource},
+    debounced_watcher::DebouncedWatcher,
+    ext::PairExt,
+    game_event_log::{GameEvent, GameEventLog},
+    run_summary::RunSummary,
+    settings::{AutoStartCondition, Settings},
+    state_machine_debugger::StateMachineDebugger,
+};
+
+#[derive(Copy, Clone, Debug)]
 pub enum Split {
     Mountain,
     Town,
@@ -10,6 +26,42 @@ pub enum Split {
     LevelUp,
     Dungeon,
     Boss,
+    Milestone(u32),
+    Credits,
+    TutorialSkipped,
+    AbilityAcquired,
+    Encounters(u32),
+    PartyMemberJoined,
+    Died,
+    BossLowHP,
+    FullHeal,
+    ItemCollected,
+    WorldMap,
+    AnyEncounterDone,
+    BossPhase(u8),
+}
+
+impl Split {
+    /// Splits that are still prone to false positives and need to be opted into.
+    pub fn is_experimental(&self) -> bool {
+        matches!(
+            self,
+            Self::BossLowHP | Self::BossPhase(_) | Self::AnyEncounterDone
+        )
+    }
+
+    /// Splits that can happen any number of times in a run,
+    /// so they are not part of [`Settings::enabled_split_count`].
+    pub fn repeats(&self) -> bool {
+        matches!(
+            self,
+            Self::Encounters(_)
+                | Self::PartyMemberJoined
+                | Self::Died
+                | Self::FullHeal
+                | Self::AnyEncounterDone
+        )
+    }
 }
 
 #[derive(Debug)]
@@ -18,126 +70,1484 @@ pub enum Action {
     Split(Split),
     Pause,
     Resume,
+    SetGameTime(Duration),
+}
+
+impl Action {
+    /// Numbers the kinds of actions starting with 1, leaving 0 for no action.
+    pub fn discriminant(&self) -> u8 {
+        match self {
+            Self::ResetAndStart => 1,
+            Self::Split(_) => 2,
+            Self::Pause => 3,
+            Self::Resume => 4,
+            Self::SetGameTime(_) => 5,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct DiagInfo {
+    pub state: &'static str,
+    pub ticks_in_state: u64,
+    pub last_read_ok: bool,
+    pub load_count: usize,
+    pub pending_actions: usize,
+}
+
+/// Run stats that are shown as LiveSplit variables.
+#[derive(Debug)]
+pub struct Stats {
+    pub split_count: u8,
+    /// Like `split_count`, but without the splits that can repeat
+    pub fixed_split_count: u8,
+    pub state: &'static str,
+    pub load_count: usize,
+    pub death_count: u32,
+}
+
+impl Stats {
+    fn new() -> Self {
+        Self {
+            split_count: 0,
+            fixed_split_count: 0,
+            state: "NotRunning",
+            load_count: 0,
+            death_count: 0,
+        }
+    }
+}
+
+/// How many of the enabled splits a run has reached, for practicing partial runs.
+pub struct CompletionRating;
+
+impl CompletionRating {
+    /// The percentage of enabled splits that have been reached, at most 100.
+    pub fn compute(split_count: u8, settings: &Settings) -> u8 {
+        let possible = u32::from(settings.enabled_split_count()).max(1);
+        (u32::from(split_count) * 100 / possible).min(100) as u8
+    }
+}
+
+/// Totals over all runs since the splitter has been loaded.
+pub struct PersistentStats {
+    pub total_runs: u32,
+    pub total_deaths: u32,
+    pub total_encounters: u32,
+    deaths: Watcher<u32>,
+    encounters: Watcher<u32>,
+}
+
+impl PersistentStats {
+    pub fn new() -> Self {
+        Self {
+            total_runs: 0,
+            total_deaths: 0,
+            total_encounters: 0,
+            deaths: Watcher::new(),
+            encounters: Watcher::new(),
+        }
+    }
+
+    pub fn count_run(&mut self) {
+        self.total_runs += 1;
+    }
+
+    /// Adds what happened in the current run since the last update.
+    /// The counts go back to 0 with every new run, which is not counted.
+    /// Returns true if any of the totals changed.
+    pub fn update(&mut self, stats: &Stats, encounter_count: u32) -> bool {
+        let deaths = self.deaths.update(Some(stats.death_count)).copied();
+        let encounters = self.encounters.update(Some(encounter_count)).copied();
+
+        let mut changed = false;
+        if let Some(deaths) = deaths.filter(|d| d.increased()) {
+            self.total_deaths += deaths.current - deaths.old;
+            changed = true;
+        }
+        if let Some(encounters) = encounters.filter(|e| e.increased()) {
+            self.total_encounters += encounters.current - encounters.old;
+            changed = true;
+        }
+        changed
+    }
+}
+
+/// Counts how many encounters have been started.
+pub struct EncounterCountTracker {
+    in_encounter: Watcher<bool>,
+    count: u32,
+}
+
+impl EncounterCountTracker {
+    pub fn new() -> Self {
+        Self {
+            in_encounter: Watcher::new(),
+            count: 0,
+        }
+    }
+
+    /// Returns true if a new encounter has started.
+    pub fn update(&mut self, in_encounter: Option<bool>) -> bool {
+        let entered = self
+            .in_encounter
+            .update(in_encounter)
+            .is_some_and(|e| e.changed_to(&true));
+        if entered {
+            self.count += 1;
+        }
+        entered
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+}
+
+/// Sums up how long the party has been fighting.
+pub struct CombatTimeTracker {
+    in_combat: Watcher<bool>,
+    entered_at: u64,
+    total_ticks: u64,
+}
+
+impl CombatTimeTracker {
+    pub fn new() -> Self {
+        Self {
+            in_combat: Watcher::new(),
+            entered_at: 0,
+            total_ticks: 0,
+        }
+    }
+
+    pub fn update(&mut self, in_combat: Option<bool>, tick: u64) {
+        let Some(in_combat) = self.in_combat.update(in_combat) else {
+            return;
+        };
+        if in_combat.changed_to(&true) {
+            self.entered_at = tick;
+        } else if in_combat.changed_to(&false) {
+            self.total_ticks += tick.saturating_sub(self.entered_at);
+        }
+    }
+
+    /// Includes the combat that is still going on.
+    pub fn total_ticks(&self, tick: u64) -> u64 {
+        match self.in_combat.pair {
+            Some(in_combat) if in_combat.current => {
+                self.total_ticks + tick.saturating_sub(self.entered_at)
+            }
+            _ => self.total_ticks,
+        }
+    }
+}
+
+/// Tracks the loading flag and how long the loads take.
+pub struct LoadingWatcher {
+    loading: DebouncedWatcher<bool>,
+    load_start_tick: Option<u64>,
+    load_count: usize,
+    recent_durations: ArrayVec<u64, 4>,
+}
+
+impl LoadingWatcher {
+    pub fn new() -> Self {
+        Self {
+            loading: DebouncedWatcher::new(1),
+            load_start_tick: None,
+            load_count: 0,
+            recent_durations: ArrayVec::new(),
+        }
+    }
+
+    pub fn set_threshold(&mut self, threshold: u8) {
+        self.loading.set_threshold(threshold);
+    }
+
+    /// Returns the loading flag once it has settled.
+    pub fn update(&mut self, loading: Option<bool>, tick: u64) -> Option<Pair<bool>> {
+        let loading = self.loading.update(loading, tick).copied()?;
+
+        if loading.changed_to(&true) {
+            self.load_start_tick = Some(tick);
+            self.load_count += 1;
+        } else if loading.changed_to(&false) {
+            if let Some(start) = self.load_start_tick.take() {
+                let duration = tick.saturating_sub(start);
+                log_at!(Debug, "Load #{} took {duration} ticks", self.load_count);
+                if self.recent_durations.is_full() {
+                    self.recent_durations.remove(0);
+                }
+                self.recent_durations.push(duration);
+            }
+        }
+
+        Some(loading)
+    }
+
+    pub fn last_load_duration(&self) -> Option<u64> {
+        self.recent_durations.last().copied()
+    }
+
+    /// The durations of the last few loads, oldest first.
+    pub fn recent_load_durations(&self) -> &[u64] {
+        &self.recent_durations
+    }
 }
 
-pub struct Progress {
-    loading: Watcher<bool>,
-    splits: SplitProgression,
+pub struct Progress<S = SplitProgression> {
+    loading: LoadingWatcher,
+    stopped: Watcher<bool>,
+    play_time: Watcher<f64>,
+    splits: S,
     next: Option<Action>,
+    ticks: u64,
+    last_read_ok: bool,
+    stats: Stats,
+    encounters: EncounterCountTracker,
+    debugger: StateMachineDebugger,
+    events: GameEventLog<32>,
+    party_members: Watcher<u32>,
+    deaths: Watcher<u32>,
+    full_hp: Watcher<bool>,
+    items: Watcher<u32>,
+    abilities: Watcher<u32>,
+    equipment: Watcher<u32>,
+    /// The equipment only splits the first time it changes in a run
+    equipment_split_done: bool,
+    encounter_done: Watcher<bool>,
+    combat_time: CombatTimeTracker,
+    run_started_at: u64,
+    split_ticks: ArrayVec<(Split, u64), 32>,
+    /// The save slot that the current run has been started on
+    save_slot: Option<u32>,
 }
 
-impl Progress {
+impl<S: SplitLogic> Progress<S> {
     pub fn new() -> Self {
         Self {
-            loading: Watcher::new(),
-            splits: SplitProgression::new(),
+            loading: LoadingWatcher::new(),
+            stopped: Watcher::new(),
+            play_time: Watcher::new(),
+            splits: S::default(),
             next: None,
+            ticks: 0,
+            last_read_ok: false,
+            stats: Stats::new(),
+            encounters: EncounterCountTracker::new(),
+            debugger: StateMachineDebugger::new(),
+            events: GameEventLog::new(),
+            party_members: Watcher::new(),
+            deaths: Watcher::new(),
+            full_hp: Watcher::new(),
+            items: Watcher::new(),
+            abilities: Watcher::new(),
+            equipment: Watcher::new(),
+            equipment_split_done: false,
+            encounter_done: Watcher::new(),
+            combat_time: CombatTimeTracker::new(),
+            run_started_at: 0,
+            split_ticks: ArrayVec::new(),
+            save_slot: None,
         }
     }
 
-    pub fn act(&mut self, data: &Data<'_>) -> Option<Action> {
+    pub fn tick(&mut self) {
+        self.ticks += 1;
+    }
+
+    pub fn act_diag(
+        &mut self,
+        data: &mut impl GameDataSource,
+        settings: &Settings,
+    ) -> (Option<Action>, DiagInfo) {
+        let action = self.act(data, settings);
+        self.debugger
+            .record(self.splits.discriminant(), action.as_ref());
+        if let Some(action @ Action::Split(split)) = &action {
+            if settings.allows(action) {
+                self.stats.split_count = self.stats.split_count.saturating_add(1);
+                if !split.repeats() {
+                    self.stats.fixed_split_count = self.stats.fixed_split_count.saturating_add(1);
+                }
+                let _ = self.split_ticks.try_push((*split, self.ticks));
+            }
+        }
+        match action {
+            Some(Action::Split(Split::LevelUp)) => {
+                self.events.record(GameEvent::LevelUp, self.ticks)
+            }
+            Some(Action::Split(Split::Boss)) => {
+                log_at!(Debug, "State machine history: {}", self.debugger);
+                self.events.dump();
+                RunSummary::capture(self, data, self.ticks).print();
+            }
+            Some(Action::ResetAndStart) => self.begin_run(data),
+            _ => {}
+        }
+        self.stats.state = self.splits.name();
+
+        let diag = DiagInfo {
+            state: self.splits.name(),
+            ticks_in_state: self.splits.ticks_in_current_state(self.ticks),
+            last_read_ok: self.last_read_ok,
+            load_count: self.level_loads_count().unwrap_or(0),
+            pending_actions: self.pending_action_count(),
+        };
+        // Only one action can be deferred, more would have been lost
+        if cfg!(debug_assertions) && diag.pending_actions > 1 {
+            log_at!(Warn, "{} actions are pending", diag.pending_actions);
+        }
+
+        (action, diag)
+    }
+
+    fn act(&mut self, data: &mut impl GameDataSource, settings: &Settings) -> Option<Action> {
         if let Some(next) = self.next.take() {
             return Some(next);
         }
 
-        match self.loading.update(data.is_loading()) {
-            Some(l) if l.changed_to(&false) => Some(Action::Resume),
-            Some(l) if l.changed_to(&true) => {
-                self.next = self.splits.act(true, data);
+        if self.splits.is_running() {
+            self.combat_time.update(data.is_in_combat(), self.ticks);
+        }
+
+        let play_time_reset = data.play_time_reset_detected(&mut self.play_time);
+        if settings.toggles.allow_mid_run_reset
+            && settings.auto_start != AutoStartCondition::Manual
+            && play_time_reset
+        {
+            if let Some(action) = self
+                .splits
+                .handle_unexpected_reset(data, settings, self.ticks)
+            {
+                return Some(action);
+            }
+        }
+
+        if self.splits.is_running() {
+            self.check_save_slot(data);
+        }
+
+        if settings.toggles.use_play_time
+            && self.splits.is_running()
+            && self
+                .play_time
+                .pair
+                .is_some_and(|pt| pt.map(|secs| secs as u64).changed())
+        {
+            if let Some(game_time) = data.game_time_duration() {
+                return Some(Action::SetGameTime(game_time));
+            }
+        }
+
+        if self.splits.is_running() && self.encounters.update(data.in_encounter()) {
+            let count = self.encounters.count();
+            log_at!(Debug, "Started encounter #{count}");
+            self.events.record(GameEvent::EncounterStarted, self.ticks);
+            if settings.toggles.split_on_encounter_start {
+                return Some(Action::Split(Split::Encounters(count)));
+            }
+        }
+
+        let party_members = self.party_members.update(data.party_member_count());
+        if party_members.is_some_and(|m| m.increased()) && self.splits.is_running() {
+            self.events.record(GameEvent::PartyMemberJoined, self.ticks);
+            return Some(Action::Split(Split::PartyMemberJoined));
+        }
+
+        let deaths = self.deaths.update(data.total_death_count());
+        if let Some(deaths) = deaths.filter(|d| d.increased()) {
+            log_at!(Debug, "Party died, {} deaths in total", deaths.current);
+            if self.splits.is_running() {
+                self.stats.death_count += 1;
+                self.events.record(GameEvent::Died, self.ticks);
+                return Some(Action::Split(Split::Died));
+            }
+        }
+
+        if settings.toggles.split_on_full_heal {
+            let full_hp = self.full_hp.update(data.is_party_full_hp());
+            if full_hp.is_some_and(|f| f.changed_to(&true)) && self.splits.is_running() {
+                return Some(Action::Split(Split::FullHeal));
+            }
+        }
+
+        if settings.toggles.split_on_item {
+            let items = self.items.update(data.item_count());
+            if items.is_some_and(|i| i.increased() && i.current == settings.split_item_count)
+                && self.splits.is_running()
+            {
+                return Some(Action::Split(Split::ItemCollected));
+            }
+        }
+
+        // Checked on the side, so that the route goes on when the count is never reached
+        if settings.ability_count_split > 0 {
+            let target = settings.ability_count_split;
+            let abilities = self.abilities.update(data.current_abilities_count());
+            if abilities.is_some_and(|a| a.old < target && a.current >= target)
+                && self.splits.is_running()
+            {
+                return Some(Action::Split(Split::AbilityAcquired));
+            }
+        }
+
+        if settings.toggles.split_on_equipment_change && !self.equipment_split_done {
+            let equipment = self.equipment.update(data.current_equipment_count());
+            if equipment.is_some_and(|e| e.changed()) && self.splits.is_running() {
+                self.equipment_split_done = true;
+                return Some(Action::Split(Split::Milestone(0)));
+            }
+        }
+
+        if settings.toggles.split_all_encounters {
+            // The route splits are decided on the next `act` call in the same tick,
+            // their watchers have not seen this value yet
+            let done = self.encounter_done.update(data.is_encounter_done());
+            if done.is_some_and(|d| d.changed_to(&true)) && self.splits.is_running() {
+                return Some(Action::Split(Split::AnyEncounterDone));
+            }
+        }
+
+        let loading = data.is_loading();
+        self.last_read_ok = loading.is_some();
+
+        self.loading.set_threshold(settings.loading_debounce_ticks);
+        let loading = self.loading.update(loading, self.ticks);
+        let started_loading = loading.is_some_and(|l| l.changed_to(&true));
+
+        // A paused game stops the timer like a load, but does not count as a level load
+        let paused = settings.toggles.stop_when_loading && data.is_paused().unwrap_or(false);
+        let stopped = self
+            .stopped
+            .update(loading.map(|l| l.current || paused))
+            .copied();
+        // Loads before the start of a run are not part of the run stats
+        if started_loading && self.splits.is_running() {
+            self.stats.load_count += 1;
+            self.events.record(GameEvent::LevelLoad, self.ticks);
+        }
+        let action = self.splits.act(started_loading, data, settings, self.ticks);
+
+        match stopped {
+            Some(s) if s.changed_to(&false) => {
+                self.next = action;
+                Some(Action::Resume)
+            }
+            Some(s) if s.changed_to(&true) => {
+                self.next = action;
                 Some(Action::Pause)
             }
-            _ => self.splits.act(false, &data),
+            _ => action,
+        }
+    }
+
+    /// Loading a different save in the middle of a run leaves the watchers with stale values.
+    fn check_save_slot(&mut self, data: &impl GameDataSource) {
+        let Some(slot) = data.current_save_slot() else {
+            return;
+        };
+        match self.save_slot.replace(slot) {
+            Some(old) if old != slot => {
+                log_at!(
+                    Warn,
+                    "Save slot changed: {old} → {slot}, possible run contamination"
+                );
+            }
+            _ => {}
+        }
+    }
+
+    pub fn level_loads_count(&self) -> Option<usize> {
+        self.splits.level_loads_count()
+    }
+
+    /// The number of actions that have been deferred to the next call of `act`.
+    pub fn pending_action_count(&self) -> usize {
+        usize::from(self.next.is_some())
+    }
+
+    pub fn encounter_count(&self) -> u32 {
+        self.encounters.count()
+    }
+
+    pub fn loading(&self) -> &LoadingWatcher {
+        &self.loading
+    }
+
+    /// The splits that count towards [`Self::expected_total_splits`].
+    pub fn fixed_split_count(&self) -> u8 {
+        self.stats.fixed_split_count
+    }
+
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    pub fn run_started_at(&self) -> u64 {
+        self.run_started_at
+    }
+
+    /// The allowed splits of the current run, together with the tick at which they happened.
+    pub fn split_ticks(&self) -> &[(Split, u64)] {
+        &self.split_ticks
+    }
+
+    pub fn combat_ticks(&self) -> u64 {
+        self.combat_time.total_ticks(self.ticks)
+    }
+
+    pub fn expected_total_splits(&self, settings: &Settings) -> u8 {
+        settings.enabled_split_count()
+    }
+
+    pub fn current_state_name(&self) -> &'static str {
+        self.splits.name()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.splits.is_running()
+    }
+
+    /// Starts tracking a run that the runner has started themselves.
+    pub fn start_manually(&mut self, data: &impl GameDataSource, settings: &Settings) {
+        // The timer is already running, so there is no need to start it again
+        let _ = self.splits.start(data, settings, self.ticks);
+        self.begin_run(data);
+    }
+
+    /// The previous run does not always end with a reset of the timer, e.g. after the boss
+    /// or when the game is restarted, so the counters of the run are cleared here.
+    fn begin_run(&mut self, data: &impl GameDataSource) {
+        self.stats = Stats::new();
+        self.encounters = EncounterCountTracker::new();
+        self.events = GameEventLog::new();
+        self.combat_time = CombatTimeTracker::new();
+        self.split_ticks.clear();
+        self.run_started_at = self.ticks;
+        self.save_slot = data.current_save_slot();
+        self.equipment_split_done = false;
+    }
+
+    /// Returns the abandoned state if there was a run in progress.
+    pub fn reset(&mut self) -> Option<S> {
+        if !self.splits.is_running() {
+            return None;
+        }
+
+        self.events.dump();
+        Some(core::mem::take(self).splits)
+    }
+}
+
+impl<S: SplitLogic> Default for Progress<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Progress<SplitProgression> {
+    pub fn boss_enemy(&self) -> Option<Address64> {
+        self.splits.boss_enemy()
+    }
+
+    pub fn next_expected_split(&self) -> Option<Split> {
+        self.splits.next_expected_split()
+    }
+}
+
+/// The number of level loads after which the party is in the dungeon.
+const DUNGEON_LEVEL_LOADS: usize = 4;
+
+/// Creates a [`Progress`] that starts somewhere in the middle of a run.
+#[cfg(test)]
+pub struct ProgressBuilder {
+    start: StartPoint,
+}
+
+#[cfg(test)]
+enum StartPoint {
+    Beginning,
+    AfterMob,
+}
+
+#[cfg(test)]
+impl ProgressBuilder {
+    pub fn new() -> Self {
+        Self {
+            start: StartPoint::Beginning,
         }
     }
 
-    pub fn reset(&mut self) {
-        if !matches!(self.splits, SplitProgression::NotRunning { .. }) {
-            *self = Progress::new();
+    pub fn start_after_mob(mut self) -> Self {
+        self.start = StartPoint::AfterMob;
+        self
+    }
+
+    pub fn build(self) -> Progress {
+        let splits = match self.start {
+            StartPoint::Beginning => SplitProgression::Started {
+                level_loads: 0,
+                tutorial_skipped: false,
+                world_map_split_done: false,
+                loads_since_last_split: 0,
+                entered_at: 0,
+            },
+            StartPoint::AfterMob => SplitProgression::dungeon_again(0),
+        };
+
+        Progress {
+            splits,
+            ..Progress::new()
         }
     }
 }
 
-enum SplitProgression {
-    NotRunning { play_time: Watcher<u64> },
-    Started { level_loads: usize },
-    InDungeon,
-    AgainstMob,
-    DungeonAgain { party_level: Watcher<u32> },
-    Leveled,
-    EncounteredFinalBoss { enemy: Address64, hp: Watcher<u32> },
+#[cfg(test)]
+impl Default for ProgressBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A route through the game, deciding when to start and split.
+pub trait SplitLogic: Default {
+    fn act(
+        &mut self,
+        loading: bool,
+        data: &mut impl GameDataSource,
+        settings: &Settings,
+        tick: u64,
+    ) -> Option<Action>;
+
+    /// Called when the game has been restarted while the run is going on.
+    fn handle_unexpected_reset(
+        &mut self,
+        data: &impl GameDataSource,
+        settings: &Settings,
+        tick: u64,
+    ) -> Option<Action>;
+
+    fn start(&mut self, data: &impl GameDataSource, settings: &Settings, tick: u64) -> Action;
+
+    fn name(&self) -> &'static str;
+
+    /// Numbers the states for the [`StateMachineDebugger`].
+    fn discriminant(&self) -> u8;
+
+    fn is_running(&self) -> bool;
+
+    fn ticks_in_current_state(&self, current_tick: u64) -> u64;
+
+    fn level_loads_count(&self) -> Option<usize> {
+        None
+    }
+}
+
+pub enum SplitProgression {
+    NotRunning {
+        play_time: Watcher<f64>,
+        in_combat: Watcher<bool>,
+        entered_at: u64,
+    },
+    Started {
+        level_loads: usize,
+        tutorial_skipped: bool,
+        world_map_split_done: bool,
+        loads_since_last_split: usize,
+        entered_at: u64,
+    },
+    InDungeon {
+        entered_at: u64,
+    },
+    AgainstMob {
+        total_hp: Watcher<u32>,
+        all_defeated: Watcher<bool>,
+        snapshot: Option<EncounterHpSnapshot>,
+        wave_number: u32,
+        entered_at: u64,
+    },
+    DungeonAgain {
+        consecutive_none_count: u16,
+        entered_at: u64,
+    },
+    Leveled {
+        entered_at: u64,
+    },
+    EncounteredFinalBoss {
+        enemy: Address64,
+        hp: AddressKeyedWatcher<u32>,
+        enemy_hps: ArrayMap<Address64, Watcher<u32>, 8>,
+        start_hp: u32,
+        /// Both the boss phases and the low HP split are relative to this
+        start_max_hp: u32,
+        /// The HP at which each boss phase starts, in descending order
+        phase_thresholds: [u32; 3],
+        phase2_emitted: bool,
+        current_phase: u8,
+        entered_at: u64,
+    },
+    Credits {
+        credits_watcher: Watcher<bool>,
+        entered_at: u64,
+    },
+    /// The run is over, a new run can only start after the cooldown.
+    Completed {
+        completed_at: u64,
+    },
+}
+
+impl Default for SplitProgression {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SplitLogic for SplitProgression {
+    fn act(
+        &mut self,
+        loading: bool,
+        data: &mut impl GameDataSource,
+        settings: &Settings,
+        tick: u64,
+    ) -> Option<Action> {
+        let state = self.name();
+        let ticks = self.ticks_in_current_state(tick);
+        let action = self.act(loading, data, settings, tick);
+        if self.name() != state {
+            log_at!(Debug, "Leaving {state} after {ticks} ticks");
+        }
+        action
+    }
+
+    fn handle_unexpected_reset(
+        &mut self,
+        data: &impl GameDataSource,
+        settings: &Settings,
+        tick: u64,
+    ) -> Option<Action> {
+        self.handle_unexpected_reset(data, settings, tick)
+    }
+
+    fn start(&mut self, data: &impl GameDataSource, settings: &Settings, tick: u64) -> Action {
+        self.start(data, settings, tick)
+    }
+
+    fn name(&self) -> &'static str {
+        self.name()
+    }
+
+    fn discriminant(&self) -> u8 {
+        self.discriminant()
+    }
+
+    fn is_running(&self) -> bool {
+        !matches!(self, Self::NotRunning { .. } | Self::Completed { .. })
+    }
+
+    fn ticks_in_current_state(&self, current_tick: u64) -> u64 {
+        self.ticks_in_current_state(current_tick)
+    }
+
+    fn level_loads_count(&self) -> Option<usize> {
+        self.level_loads_count()
+    }
 }
 
 impl SplitProgression {
     fn new() -> Self {
-        let mut play_time = Watcher::new();
-        play_time.update_infallible(u64::MAX);
-        Self::NotRunning { play_time }
+        Self::NotRunning {
+            play_time: Watcher::new(),
+            in_combat: Watcher::new(),
+            entered_at: 0,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::NotRunning { .. } => "NotRunning",
+            Self::Started { .. } => "Started",
+            Self::InDungeon { .. } => "InDungeon",
+            Self::AgainstMob { .. } => "AgainstMob",
+            Self::DungeonAgain { .. } => "DungeonAgain",
+            Self::Leveled { .. } => "Leveled",
+            Self::EncounteredFinalBoss { .. } => "EncounteredFinalBoss",
+            Self::Credits { .. } => "Credits",
+            Self::Completed { .. } => "Completed",
+        }
+    }
+
+    pub fn discriminant(&self) -> u8 {
+        match self {
+            Self::NotRunning { .. } => 0,
+            Self::Started { .. } => 1,
+            Self::InDungeon { .. } => 2,
+            Self::AgainstMob { .. } => 3,
+            Self::DungeonAgain { .. } => 4,
+            Self::Leveled { .. } => 5,
+            Self::EncounteredFinalBoss { .. } => 6,
+            Self::Credits { .. } => 7,
+            Self::Completed { .. } => 8,
+        }
+    }
+
+    fn dungeon_again(tick: u64) -> Self {
+        Self::DungeonAgain {
+            consecutive_none_count: 0,
+            entered_at: tick,
+        }
+    }
+
+    pub fn skip(&mut self, n: usize, tick: u64) {
+        for _ in 0..n {
+            let from = self.name();
+            self.advance(tick);
+            log_at!(Info, "Skipped a split: {from} -> {}", self.name());
+        }
+    }
+
+    fn advance(&mut self, tick: u64) {
+        match self {
+            Self::NotRunning { .. } => {}
+            Self::Started { level_loads, .. } if *level_loads < 3 => {
+                *level_loads = (*level_loads + 1).max(2);
+            }
+            Self::Started { .. } | Self::InDungeon { .. } | Self::AgainstMob { .. } => {
+                *self = Self::dungeon_again(tick);
+            }
+            Self::DungeonAgain { .. } => {
+                *self = Self::Leveled { entered_at: tick };
+            }
+            // The final boss can only be tracked once it has been encountered
+            Self::Leveled { .. }
+            | Self::EncounteredFinalBoss { .. }
+            | Self::Credits { .. }
+            | Self::Completed { .. } => {}
+        }
+    }
+
+    pub fn level_loads_count(&self) -> Option<usize> {
+        match self {
+            Self::Started { level_loads, .. } => Some(*level_loads),
+            _ => None,
+        }
+    }
+
+    /// The split that the current state is waiting for, regardless of whether it is enabled.
+    /// Optional splits that can happen on the side, like deaths, are not considered.
+    pub fn next_expected_split(&self) -> Option<Split> {
+        match self {
+            Self::NotRunning { .. } | Self::Completed { .. } => None,
+            Self::Started { level_loads, .. } if *level_loads < 2 => Some(Split::Mountain),
+            Self::Started { level_loads, .. } if *level_loads < 3 => Some(Split::Town),
+            Self::Started { .. } | Self::InDungeon { .. } | Self::AgainstMob { .. } => {
+                Some(Split::Mob)
+            }
+            Self::DungeonAgain { .. } => Some(Split::LevelUp),
+            Self::Leveled { .. } => Some(Split::Dungeon),
+            Self::EncounteredFinalBoss { .. } => Some(Split::Boss),
+            Self::Credits { .. } => Some(Split::Credits),
+        }
+    }
+
+    fn entered_at(&self) -> u64 {
+        match self {
+            Self::NotRunning { entered_at, .. }
+            | Self::Started { entered_at, .. }
+            | Self::InDungeon { entered_at, .. }
+            | Self::AgainstMob { entered_at, .. }
+            | Self::DungeonAgain { entered_at, .. }
+            | Self::Leveled { entered_at }
+            | Self::EncounteredFinalBoss { entered_at, .. }
+            | Self::Credits { entered_at, .. } => *entered_at,
+            Self::Completed { completed_at } => *completed_at,
+        }
+    }
+
+    pub fn ticks_in_current_state(&self, current_tick: u64) -> u64 {
+        current_tick.saturating_sub(self.entered_at())
+    }
+
+    pub fn handle_unexpected_reset(
+        &mut self,
+        data: &impl GameDataSource,
+        settings: &Settings,
+        tick: u64,
+    ) -> Option<Action> {
+        if matches!(self, Self::NotRunning { .. } | Self::Completed { .. }) {
+            return None;
+        }
+
+        log_at!(
+            Info,
+            "Game restarted during the run in state {}",
+            self.name()
+        );
+        // Going through `NotRunning` would wait for the next time the play time hits zero,
+        // but that already happened, so we start the new run right away.
+        Some(self.start(data, settings, tick))
+    }
+
+    fn start(&mut self, data: &impl GameDataSource, settings: &Settings, tick: u64) -> Action {
+        // The tutorial has one level load that will not happen when it is skipped
+        const TUTORIAL_LEVEL_LOADS: usize = 1;
+
+        let tutorial_skipped =
+            settings.toggles.tutorial_skip && data.tutorial_completed().unwrap_or(false);
+        *self = Self::Started {
+            level_loads: if tutorial_skipped {
+                TUTORIAL_LEVEL_LOADS
+            } else {
+                0
+            },
+            tutorial_skipped,
+            world_map_split_done: false,
+            loads_since_last_split: 0,
+            entered_at: tick,
+        };
+        self.skip(settings.auto_skip_count as usize, tick);
+        Action::ResetAndStart
     }
 
-    fn act(&mut self, loading: bool, data: &Data<'_>) -> Option<Action> {
+    pub fn boss_enemy(&self) -> Option<Address64> {
+        match self {
+            Self::EncounteredFinalBoss { enemy, .. } => Some(*enemy),
+            _ => None,
+        }
+    }
+
+    fn act(
+        &mut self,
+        loading: bool,
+        data: &mut impl GameDataSource,
+        settings: &Settings,
+        tick: u64,
+    ) -> Option<Action> {
+        let ticks = self.ticks_in_current_state(tick);
+        if self.is_running() && ticks == settings.stall_threshold_ticks {
+            log_at!(Warn, "State stalled: {} for {ticks} ticks", self.name());
+        }
+
         match self {
-            Self::NotRunning { play_time } => {
-                let play_time = play_time.update(data.play_time());
-                if play_time.is_some_and(|pt| pt.changed_to(&0)) {
-                    *self = Self::Started { level_loads: 0 };
-                    return Some(Action::ResetAndStart);
+            Self::NotRunning {
+                play_time,
+                in_combat,
+                ..
+            } => {
+                let start = match settings.auto_start {
+                    AutoStartCondition::PlayTimeZero => {
+                        // A new game only starts when the play time goes back to 0
+                        // from a previous value, the same as a reset during the run.
+                        data.play_time_reset_detected(play_time)
+                    }
+                    AutoStartCondition::FirstLevelLoad => loading,
+                    AutoStartCondition::FirstCombatEntered => {
+                        let phase = data.combat_phase();
+                        let fighting = phase.map(|p| matches!(p, CombatPhase::InCombat { .. }));
+                        in_combat
+                            .update(fighting)
+                            .is_some_and(|c| c.changed_to(&true))
+                    }
+                    AutoStartCondition::Manual => false,
+                };
+                if start {
+                    return Some(self.start(data, settings, tick));
                 }
             }
-            Self::Started { level_loads } => {
-                if loading {
+            Self::Started {
+                level_loads,
+                tutorial_skipped,
+                world_map_split_done,
+                loads_since_last_split,
+                ..
+            } => {
+                if core::mem::take(tutorial_skipped) {
+                    *loads_since_last_split = 0;
+                    return Some(Action::Split(Split::TutorialSkipped));
+                }
+
+                // The world map can be unlocked at any point of the level loads
+                if settings.toggles.split_world_map
+                    && !*world_map_split_done
+                    && data.is_world_map_unlocked().unwrap_or(false)
+                {
+                    *world_map_split_done = true;
+                    *loads_since_last_split = 0;
+                    return Some(Action::Split(Split::WorldMap));
+                }
+
+                let counted = loading && !data.is_cutscene().unwrap_or(false);
+                if counted {
                     *level_loads += 1;
+                    *loads_since_last_split += 1;
+                    // An extra or a missing load shifts all of the following splits
                     match *level_loads {
-                        2 => return Some(Action::Split(Split::Mountain)),
-                        3 => return Some(Action::Split(Split::Town)),
-                        4 => {
-                            *self = Self::InDungeon;
+                        2 => {
+                            log_at!(Debug, "Mountain split after {loads_since_last_split} loads");
+                            *loads_since_last_split = 0;
+                            return Some(Action::Split(Split::Mountain));
+                        }
+                        3 => {
+                            log_at!(Debug, "Town split after {loads_since_last_split} loads");
+                            *loads_since_last_split = 0;
+                            return Some(Action::Split(Split::Town));
+                        }
+                        DUNGEON_LEVEL_LOADS => {
+                            *self = Self::InDungeon { entered_at: tick };
                         }
                         _ => {}
                     }
                 }
             }
-            Self::InDungeon => {
+            Self::InDungeon { .. } => {
                 let encounter_size = data.encounter_size();
-                if encounter_size.is_some_and(|es| es == 4) {
-                    *self = Self::AgainstMob;
+                // Keep watching the encounter so that a stale done flag is not seen as a change
+                data.encounter_done();
+
+                // The encounter size only confirms that we are fighting the right mob,
+                // it can be stale when the combat flag is not available
+                let fighting = data
+                    .combat_phase()
+                    .is_none_or(|phase| matches!(phase, CombatPhase::InCombat { .. }));
+                if fighting
+                    && encounter_size.is_some_and(|es| {
+                        settings.is_mob_encounter_size(es.old)
+                            || settings.is_mob_encounter_size(es.current)
+                    })
+                    && !data.is_cutscene().unwrap_or(false)
+                {
+                    *self = Self::AgainstMob {
+                        total_hp: Watcher::new(),
+                        all_defeated: Watcher::new(),
+                        snapshot: data.encounter_hp_snapshot(),
+                        wave_number: 1,
+                        entered_at: tick,
+                    };
                 }
             }
-            Self::AgainstMob => {
-                let encounter_done = data.encounter_done();
-                if encounter_done.is_some_and(|d| d) {
-                    *self = Self::DungeonAgain {
-                        party_level: Watcher::new(),
-                    };
+            // act can run more than once per tick, so the timeout is measured in ticks
+            Self::AgainstMob { .. }
+                if self.ticks_in_current_state(tick) >= settings.combat_timeout_ticks =>
+            {
+                log_at!(
+                    Warn,
+                    "Encounter did not finish in time, going back to the dungeon"
+                );
+                *self = Self::InDungeon { entered_at: tick };
+                return None;
+            }
+            Self::AgainstMob {
+                total_hp,
+                all_defeated,
+                snapshot,
+                wave_number,
+                ..
+            } => {
+                if snapshot.is_none() || data.is_encounter_new() {
+                    *snapshot = data.encounter_hp_snapshot();
+                }
+
+                let defeated = if settings.toggles.split_on_total_hp_zero {
+                    let total_hp = total_hp.update(data.total_hp_remaining());
+                    total_hp.is_some_and(|hp| hp.changed_to(&0))
+                } else {
+                    let encounter_done = data.encounter_done().is_some_and(|d| d.changed_to(&true));
+                    // Like the done flag, this only counts once per wave
+                    let all_defeated = all_defeated
+                        .update(data.all_enemies_defeated())
+                        .is_some_and(|d| d.changed_to(&true));
+                    encounter_done || all_defeated
+                };
+                if defeated {
+                    // Only the last wave of reinforcements finishes the encounter
+                    let wave_count = data.encounter_wave_count().unwrap_or(0);
+                    if wave_count > *wave_number {
+                        let remaining = wave_count - *wave_number;
+                        log_at!(Debug, "Wave {wave_number} complete, {remaining} waves left");
+                        *wave_number += 1;
+                        return None;
+                    }
+
+                    if let Some(snapshot) = snapshot {
+                        let remaining = data.total_hp_remaining().unwrap_or(0);
+                        log_at!(
+                            Debug,
+                            "Mob went down from {:?} ({} HP in total) to {remaining} HP",
+                            snapshot.hps,
+                            snapshot.total
+                        );
+                    }
+                    *self = Self::dungeon_again(tick);
                     return Some(Action::Split(Split::Mob));
                 }
             }
-            Self::DungeonAgain { party_level } => {
-                let level = party_level.update(data.party_level());
-                if level.is_some_and(|l| l.changed_to(&4)) {
-                    *self = Self::Leveled;
-                    return Some(Action::Split(Split::LevelUp));
+            Self::DungeonAgain {
+                consecutive_none_count,
+                ..
+            } => {
+                let Some(party_level) = data.party_level_change() else {
+                    *consecutive_none_count = consecutive_none_count.saturating_add(1);
+                    match *consecutive_none_count {
+                        60 => log_at!(
+                            Warn,
+                            "party_level read failing for 60 ticks, address may be invalid"
+                        ),
+                        // A failed read already starts the level watcher over,
+                        // so there is nothing to reset here
+                        600 => log_at!(
+                            Error,
+                            "party_level read failing for 600 ticks, the level up can not be detected"
+                        ),
+                        _ => {}
+                    }
+                    return None;
+                };
+                *consecutive_none_count = 0;
+
+                if !party_level.increased() {
+                    return None;
+                }
+                // Every level up splits, but only the target level finishes the dungeon
+                if party_level.current == settings.level_up_target {
+                    *self = Self::Leveled { entered_at: tick };
                 }
+                return Some(Action::Split(Split::LevelUp));
             }
-            Self::Leveled => {
+            Self::Leveled { .. } => {
                 let (enemy, encounter_hp) = data.first_enemy_start_hp().unwrap_or_default();
+                if encounter_hp == settings.boss_hp && !data.is_cutscene().unwrap_or(false) {
+                    log_at!(
+                        Debug,
+                        "Final boss is {:?} with id {:?}",
+                        data.first_enemy_name(),
+                        data.first_enemy_id()
+                    );
+                    let mut hp = AddressKeyedWatcher::new(enemy);
+                    hp.update(enemy, Some(encounter_hp));
+                    let start_max_hp = data.enemy_max_hp(enemy).unwrap_or(encounter_hp);
 
-                if encounter_hp == 700 {
-                    let mut hp = Watcher::new();
-                    hp.update_infallible(700);
-
-                    *self = Self::EncounteredFinalBoss { enemy, hp };
+                    *self = Self::EncounteredFinalBoss {
+                        enemy,
+                        hp,
+                        enemy_hps: ArrayMap::new(),
+                        start_hp: encounter_hp,
+                        start_max_hp,
+                        phase_thresholds: [
+                            start_max_hp / 4 * 3,
+                            start_max_hp / 2,
+                            start_max_hp / 4,
+                        ],
+                        phase2_emitted: false,
+                        current_phase: 0,
+                        entered_at: tick,
+                    };
                     return Some(Action::Split(Split::Dungeon));
                 }
             }
-            Self::EncounteredFinalBoss { hp, enemy } => {
-                let hp = hp.update(data.current_hp(*enemy));
-                if hp.is_some_and(|hp| hp.changed_to(&0)) {
-                    *self = Self::new();
+            Self::EncounteredFinalBoss {
+                hp,
+                enemy,
+                enemy_hps,
+                start_hp,
+                start_max_hp,
+                phase_thresholds,
+                phase2_emitted,
+                current_phase,
+                ..
+            } => {
+                for other in data.encounter_enemies().unwrap_or_default() {
+                    if enemy_hps.get(&other).is_none() {
+                        enemy_hps.insert(other, Watcher::new());
+                    }
+                    let Some(other_hp) = enemy_hps.get_mut(&other) else {
+                        continue;
+                    };
+                    let other_hp = other_hp.update(data.current_hp(other)).copied();
+                    match other_hp {
+                        Some(other_hp) if other_hp.changed_to(&0) => {
+                            log_at!(Debug, "Enemy at 0x{:x} went down", other.value());
+                        }
+                        Some(_) => {}
+                        // The enemy has left the encounter
+                        None => {
+                            enemy_hps.remove(&other);
+                        }
+                    }
+                }
+
+                // Only the boss itself counts, even when another enemy takes the first place
+                let hp = hp.update(*enemy, data.current_hp(*enemy));
+                let killed = hp.is_some_and(|hp| hp.changed_to(&0));
+                // Healing back above a threshold does not repeat the split of that phase
+                if settings.toggles.split_boss_hp_quarters {
+                    let phase = data.encounter_hp_phase(*enemy, phase_thresholds);
+                    if let Some(phase) = phase.filter(|&phase| !killed && phase > *current_phase) {
+                        *current_phase = phase;
+                        return Some(Action::Split(Split::BossPhase(phase)));
+                    }
+                }
+
+                let low_hp = *start_max_hp / 4;
+                if !killed
+                    && !*phase2_emitted
+                    && hp.is_some_and(|hp| hp.decreased() && hp.current <= low_hp)
+                {
+                    *phase2_emitted = true;
+                    return Some(Action::Split(Split::BossLowHP));
+                }
+                if killed {
+                    log_at!(
+                        Debug,
+                        "Boss went down from {start_hp} HP, with {} enemies in the fight",
+                        enemy_hps.iter().count()
+                    );
+                    *self = if settings.toggles.split_on_credits_end {
+                        Self::Credits {
+                            credits_watcher: Watcher::new(),
+                            entered_at: tick,
+                        }
+                    } else {
+                        Self::Completed { completed_at: tick }
+                    };
                     return Some(Action::Split(Split::Boss));
                 }
             }
+            Self::Credits {
+                credits_watcher, ..
+            } => {
+                let playing = credits_watcher.update(data.is_credits_playing());
+                if playing.is_some_and(|p| p.changed_to(&false)) {
+                    *self = Self::Completed { completed_at: tick };
+                    return Some(Action::Split(Split::Credits));
+                }
+            }
+            Self::Completed { completed_at } => {
+                // Give the runner time before a new game can start the next run
+                if tick.saturating_sub(*completed_at) >= u64::from(settings.post_run_cooldown_ticks)
+                {
+                    *self = Self::NotRunning {
+                        play_time: Watcher::new(),
+                        in_combat: Watcher::new(),
+                        entered_at: tick,
+                    };
+                }
+            }
         };
 
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use asr::Address64;
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::settings::Toggles;
+    use crate::test_utils::{
+        route_settings, run_tick, FixturePlayer, MockData, SYNTHETIC_DEMO_RUN_FIXTURE,
+    };
+
+    fn load_level(
+        progress: &mut Progress,
+        data: &mut MockData,
+        settings: &Settings,
+    ) -> Vec<Action> {
+        data.is_loading = Some(true);
+        let mut actions = run_tick(progress, data, settings);
+        data.is_loading = Some(false);
+        actions.extend(run_tick(progress, data, settings));
+        actions
+    }
+
+    #[test]
+    fn splits_the_route_from_the_mountain_to_the_boss() {
+        let settings = route_settings();
+        let mut progress: Progress = Progress::new();
+        let mut data = MockData::new();
+        let boss = Address64::new(0x1000);
+
+        data.play_time = Some(120.0);
+        data.is_loading = Some(false);
+        data.party_level = Some(3);
+        data.encounter_size = Some(0);
+        data.encounter_done = Some(false);
+        assert!(run_tick(&mut progress, &mut data, &settings).is_empty());
+
+        // The new game has already been running for a tick
+        data.play_time = Some(0.017);
+        let actions = run_tick(&mut progress, &mut data, &settings);
+        assert!(matches!(actions[..], [Action::ResetAndStart]));
+
+        // The first load leaves the tutorial
+        assert!(load_level(&mut progress, &mut data, &settings).is_empty());
+        let actions = load_level(&mut progress, &mut data, &settings);
+        assert!(matches!(actions[..], [Action::Split(Split::Mountain)]));
+        let actions = load_level(&mut progress, &mut data, &settings);
+        assert!(matches!(actions[..], [Action::Split(Split::Town)]));
+        assert!(load_level(&mut progress, &mut data, &settings).is_empty());
+        assert_eq!(progress.current_state_name(), "InDungeon");
+
+        data.encounter_size = Some(settings.mob_encounter_size);
+        assert!(run_tick(&mut progress, &mut data, &settings).is_empty());
+        assert_eq!(progress.current_state_name(), "AgainstMob");
+        data.encounter_done = Some(true);
+        let actions = run_tick(&mut progress, &mut data, &settings);
+        assert!(matches!(actions[..], [Action::Split(Split::Mob)]));
+
+        data.encounter_size = Some(0);
+        data.encounter_done = Some(false);
+        assert!(run_tick(&mut progress, &mut data, &settings).is_empty());
+        data.party_level = Some(settings.level_up_target);
+        let actions = run_tick(&mut progress, &mut data, &settings);
+        assert!(matches!(actions[..], [Action::Split(Split::LevelUp)]));
+
+        data.first_enemy = Some((boss, 700));
+        let actions = run_tick(&mut progress, &mut data, &settings);
+        assert!(matches!(actions[..], [Action::Split(Split::Dungeon)]));
+        assert_eq!(progress.boss_enemy(), Some(boss));
+
+        data.first_enemy = Some((boss, 0));
+        let actions = run_tick(&mut progress, &mut data, &settings);
+        assert!(matches!(actions[..], [Action::Split(Split::Boss)]));
+        assert!(!progress.is_running());
+        assert_eq!(progress.stats().split_count, 6);
+        assert_eq!(progress.fixed_split_count(), settings.enabled_split_count());
+    }
+
+    #[test]
+    fn splits_the_boss_when_starting_after_the_mob() {
+        let settings = route_settings();
+        let mut progress = ProgressBuilder::new().start_after_mob().build();
+        let mut data = MockData::new();
+        let boss = Address64::new(0x1000);
+
+        data.play_time = Some(300.0);
+        data.is_loading = Some(false);
+        data.party_level = Some(3);
+        data.encounter_size = Some(0);
+        data.encounter_done = Some(false);
+        assert!(run_tick(&mut progress, &mut data, &settings).is_empty());
+        assert_eq!(progress.current_state_name(), "DungeonAgain");
+
+        data.party_level = Some(settings.level_up_target);
+        let actions = run_tick(&mut progress, &mut data, &settings);
+        assert!(matches!(actions[..], [Action::Split(Split::LevelUp)]));
+
+        data.first_enemy = Some((boss, 700));
+        let actions = run_tick(&mut progress, &mut data, &settings);
+        assert!(matches!(actions[..], [Action::Split(Split::Dungeon)]));
+
+        data.first_enemy = Some((boss, 0));
+        let actions = run_tick(&mut progress, &mut data, &settings);
+        assert!(matches!(actions[..], [Action::Split(Split::Boss)]));
+        assert_eq!(progress.stats().split_count, 3);
+    }
+
+    #[test]
+    fn times_out_the_mob_after_the_combat_timeout_in_ticks() {
+        let mut settings = route_settings();
+        settings.combat_timeout_ticks = 3;
+        let mut progress = Progress {
+            splits: SplitProgression::InDungeon { entered_at: 0 },
+            ..Progress::new()
+        };
+        let mut data = MockData::new();
+        data.is_loading = Some(false);
+        data.encounter_done = Some(false);
+        data.encounter_size = Some(settings.mob_encounter_size);
+        assert!(run_tick(&mut progress, &mut data, &settings).is_empty());
+        assert_eq!(progress.current_state_name(), "AgainstMob");
+
+        // Acting more than once in a tick must not make the timeout run faster
+        progress.tick();
+        for _ in 0..settings.combat_timeout_ticks {
+            assert!(progress.act_diag(&mut data, &settings).0.is_none());
+        }
+        assert_eq!(progress.current_state_name(), "AgainstMob");
+
+        assert!(run_tick(&mut progress, &mut data, &settings).is_empty());
+        assert_eq!(progress.current_state_name(), "AgainstMob");
+        assert!(run_tick(&mut progress, &mut data, &settings).is_empty());
+        assert_eq!(progress.current_state_name(), "InDungeon");
+    }
+
+    #[test]
+    fn splits_the_first_ability_on_the_side() {
+        let settings = Settings::with_toggles(Toggles {
+            split_on_first_ability: true,
+            ..route_settings().toggles
+        });
+        let mut progress = ProgressBuilder::new().start_after_mob().build();
+        let mut data = MockData::new();
+
+        data.play_time = Some(300.0);
+        data.is_loading = Some(false);
+        data.abilities = Some(0);
+        assert!(run_tick(&mut progress, &mut data, &settings).is_empty());
+
+        data.abilities = Some(1);
+        let actions = run_tick(&mut progress, &mut data, &settings);
+        assert!(matches!(
+            actions[..],
+            [Action::Split(Split::AbilityAcquired)]
+        ));
+        assert_eq!(progress.current_state_name(), "DungeonAgain");
+    }
+
+    #[test]
+    fn does_not_split_the_boss_when_another_enemy_goes_down() {
+        let settings = route_settings();
+        let mut progress = ProgressBuilder::new().start_after_mob().build();
+        let mut data = MockData::new();
+        let boss = Address64::new(0x1000);
+        let add = Address64::new(0x2000);
+
+        data.play_time = Some(300.0);
+        data.is_loading = Some(false);
+        data.party_level = Some(3);
+        data.encounter_size = Some(0);
+        data.encounter_done = Some(false);
+        assert!(run_tick(&mut progress, &mut data, &settings).is_empty());
+        data.party_level = Some(settings.level_up_target);
+        let actions = run_tick(&mut progress, &mut data, &settings);
+        assert!(matches!(actions[..], [Action::Split(Split::LevelUp)]));
+
+        data.first_enemy = Some((boss, 700));
+        let actions = run_tick(&mut progress, &mut data, &settings);
+        assert!(matches!(actions[..], [Action::Split(Split::Dungeon)]));
+
+        // The add takes the first place while the boss is still standing
+        data.first_enemy = Some((add, 200));
+        data.other_enemies = std::vec![(boss, 350)];
+        assert!(run_tick(&mut progress, &mut data, &settings).is_empty());
+        data.first_enemy = Some((add, 0));
+        assert!(run_tick(&mut progress, &mut data, &settings).is_empty());
+
+        data.other_enemies = std::vec![(boss, 0)];
+        let actions = run_tick(&mut progress, &mut data, &settings);
+        assert!(matches!(actions[..], [Action::Split(Split::Boss)]));
+    }
+
+    #[test]
+    fn replays_the_synthetic_demo_run() {
+        let settings = route_settings();
+        let mut progress: Progress = Progress::new();
+
+        let actions = FixturePlayer::new(SYNTHETIC_DEMO_RUN_FIXTURE).play(&mut progress, &settings);
+
+        assert!(matches!(
+            actions[..],
+            [
+                Action::ResetAndStart,
+                Action::Split(Split::Mountain),
+                Action::Split(Split::Town),
+                Action::Split(Split::Mob),
+                Action::Split(Split::LevelUp),
+                Action::Split(Split::Dungeon),
+                Action::Split(Split::Boss),
+            ]
+        ));
+    }
+}
@@ -0,0 +1,50 @@
+use arrayvec::ArrayVec;
+
+use crate::{
+    data::GameDataSource,
+    progress::{Progress, Split, SplitLogic},
+};
+
+/// Statistics of a finished run, all ticks are counted from the last reset.
+#[derive(Debug)]
+pub struct RunSummary {
+    pub started_at: u64,
+    pub splits: ArrayVec<(Split, u64), 32>,
+    pub load_count: usize,
+    pub combat_ticks: u64,
+    pub party_level: Option<u32>,
+    pub boss_killed_at: u64,
+}
+
+impl RunSummary {
+    /// The tick is the one at which the boss has been killed.
+    pub fn capture<S: SplitLogic>(
+        progress: &Progress<S>,
+        data: &impl GameDataSource,
+        tick: u64,
+    ) -> Self {
+        Self {
+            started_at: progress.run_started_at(),
+            splits: progress.split_ticks().iter().copied().collect(),
+            load_count: progress.stats().load_count,
+            combat_ticks: progress.combat_ticks(),
+            party_level: data.party_level(),
+            boss_killed_at: tick,
+        }
+    }
+
+    pub fn print(&self) {
+        log_at!(Info, "Run started at tick {}", self.started_at);
+        log_at!(Info, "{:>8} | split", "tick");
+        for (split, tick) in &self.splits {
+            log_at!(Info, "{tick:>8} | {split:?}");
+        }
+        log_at!(Info, "Loads: {}", self.load_count);
+        log_at!(Info, "Ticks in combat: {}", self.combat_ticks);
+        match self.party_level {
+            Some(level) => log_at!(Info, "Party level at the boss: {level}"),
+            None => log_at!(Info, "Party level at the boss: unknown"),
+        }
+        log_at!(Info, "Boss killed at tick {}", self.boss_killed_at);
+    }
+}
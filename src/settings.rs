@@ -3,34 +3,234 @@ use asr::user_settings::Settings;
 use crate::progress::{Action, Split};
 
 #[derive(Debug, Settings)]
-pub struct Settings {
+pub struct Toggles {
     /// Split when descending the mountain
-    mountain: bool,
+    pub mountain: bool,
     /// Split when leaving town
-    town: bool,
+    pub town: bool,
     /// Split when defeating the special mob in the blue room
-    mob: bool,
+    pub mob: bool,
     /// Split when leveled up
-    level_up: bool,
+    pub level_up: bool,
     /// Split when starting the boss fight
-    dungeon: bool,
+    pub dungeon: bool,
     /// Stop game timer during loads
-    stop_when_loading: bool,
+    pub stop_when_loading: bool,
+    /// Split when the credits have finished rolling
+    pub split_on_credits_end: bool,
+    /// Show live game values as LiveSplit variables
+    pub show_debug_panel: bool,
+    /// Start a new run when a new game is started during a run
+    #[default = true]
+    pub allow_mid_run_reset: bool,
+    /// Split the mob when the total enemy HP drops to 0 instead of when the encounter is done
+    pub split_on_total_hp_zero: bool,
+    /// Split right at the start when the tutorial has already been completed
+    pub tutorial_skip: bool,
+    /// Split when a character joins the party
+    pub split_on_party_join: bool,
+    /// Split every time the party dies
+    pub split_on_death: bool,
+    /// Split when the boss drops to a quarter of its HP
+    pub boss_low_hp_split: bool,
+    /// Run the game timer during loads and stop it otherwise, requires stopping the timer during loads
+    pub invert_pause_behavior: bool,
+    /// Split when the whole party is healed to full HP
+    pub split_on_full_heal: bool,
+    /// Split the first time the equipment changes during a run
+    pub split_on_equipment_change: bool,
+    /// Split when an item has been collected
+    pub split_on_item: bool,
+    /// Use the in-game play time as the game time
+    pub use_play_time: bool,
+    /// Log every value that is read from the game, only in debug builds
+    pub verbose_logging: bool,
+    /// Show the total runs, deaths, and encounters over all runs as LiveSplit variables
+    pub persist_stats: bool,
+    /// Split when the world map is unlocked
+    pub split_world_map: bool,
+    /// Split every time an encounter is done, in addition to the mob and the boss
+    pub split_all_encounters: bool,
+    /// Allow the experimental splits, which may split at the wrong time
+    pub experimental_features: bool,
+    /// Log the first field of every manager as raw memory, only in debug builds
+    pub enable_raw_reads: bool,
+    /// Skip every split before the final boss when a new run starts, to practice the boss
+    pub practice_boss: bool,
+    /// Split every time an encounter starts
+    pub split_on_encounter_start: bool,
+    /// Start a new run on the first load instead of when the play time goes back to 0
+    pub start_on_first_load: bool,
+    /// Start a new run on the first combat instead of when the play time goes back to 0
+    pub start_on_first_combat: bool,
+    /// Never start a new run, only follow the timer once the runner has started it
+    pub start_manually: bool,
+    /// Split when the final boss drops to three quarters, half, and a quarter of its max HP
+    pub split_boss_hp_quarters: bool,
+    /// Split when the first ability has been learned
+    pub split_on_first_ability: bool,
+}
+
+#[derive(Debug)]
+pub struct Settings {
+    pub toggles: Toggles,
+    /// The starting HP that the final boss is recognized by
+    pub boss_hp: u32,
+    /// Number of splits to skip when a new run starts
+    pub auto_skip_count: u32,
+    /// Split when this many abilities have been learned, 0 to disable
+    pub ability_count_split: u32,
+    /// Number of ticks after which a state is considered stalled
+    pub stall_threshold_ticks: u64,
+    /// Number of enemies in the mob encounter
+    pub mob_encounter_size: u32,
+    /// When to start a new run
+    pub auto_start: AutoStartCondition,
+    /// Number of collected items at which to split
+    pub split_item_count: u32,
+    /// Party level that finishes the dungeon, every level up before it also splits
+    pub level_up_target: u32,
+    /// Number of ticks after which an unfinished mob encounter is abandoned
+    pub combat_timeout_ticks: u64,
+    /// Number of ticks that the loading flag needs to stay the same before it counts as changed
+    pub loading_debounce_ticks: u8,
+    /// Number of ticks after the end of a run before a new run can start
+    pub post_run_cooldown_ticks: u32,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AutoStartCondition {
+    /// The play time goes back to 0 when a new game is started
+    PlayTimeZero,
+    FirstLevelLoad,
+    FirstCombatEntered,
+    /// Only start when the runner starts the timer
+    Manual,
 }
 
 impl Settings {
-    pub fn filter(&self, action: Action) -> Option<Action> {
-        Some(action).filter(|action| match action {
+    pub fn register() -> Self {
+        Self::with_toggles(Toggles::register())
+    }
+
+    /// Only the toggles can be changed by the runner, everything else is derived from them.
+    pub fn with_toggles(toggles: Toggles) -> Self {
+        // Mountain, town, mob, and the level up come before the final boss
+        const SPLITS_BEFORE_BOSS: u32 = 4;
+
+        let auto_skip_count = if toggles.practice_boss {
+            SPLITS_BEFORE_BOSS
+        } else {
+            0
+        };
+        let ability_count_split = u32::from(toggles.split_on_first_ability);
+        let auto_start = if toggles.start_manually {
+            AutoStartCondition::Manual
+        } else if toggles.start_on_first_load {
+            AutoStartCondition::FirstLevelLoad
+        } else if toggles.start_on_first_combat {
+            AutoStartCondition::FirstCombatEntered
+        } else {
+            AutoStartCondition::PlayTimeZero
+        };
+        Self {
+            toggles,
+            boss_hp: 700,
+            auto_skip_count,
+            ability_count_split,
+            stall_threshold_ticks: 18000,
+            mob_encounter_size: 4,
+            auto_start,
+            split_item_count: 1,
+            level_up_target: 4,
+            combat_timeout_ticks: 36000,
+            loading_debounce_ticks: 1,
+            post_run_cooldown_ticks: 300,
+        }
+    }
+
+    pub fn is_mob_encounter_size(&self, size: u32) -> bool {
+        size == self.mob_encounter_size
+    }
+
+    /// The state is the one after the action has been emitted.
+    pub fn filter_with_context(&self, action: Action, state: &'static str) -> Option<Action> {
+        Some(action).filter(|action| self.allows(action) && Self::fits_state(action, state))
+    }
+
+    fn fits_state(action: &Action, state: &'static str) -> bool {
+        match (action, state) {
+            // Loads outside of a run do not affect the game time
+            (Action::Pause | Action::Resume, "NotRunning") => false,
+            _ => true,
+        }
+    }
+
+    pub fn allows(&self, action: &Action) -> bool {
+        let toggles = &self.toggles;
+        match action {
             Action::ResetAndStart => true,
+            Action::Split(split) if split.is_experimental() && !toggles.experimental_features => {
+                false
+            }
             Action::Split(split) => match split {
-                Split::Mountain => self.mountain,
-                Split::Town => self.town,
-                Split::Mob => self.mob,
-                Split::LevelUp => self.level_up,
-                Split::Dungeon => self.dungeon,
+                Split::Mountain => toggles.mountain,
+                Split::Town => toggles.town,
+                Split::Mob => toggles.mob,
+                Split::LevelUp => toggles.level_up,
+                Split::Dungeon => toggles.dungeon,
                 Split::Boss => true,
+                Split::Milestone(_) => true,
+                Split::Credits => toggles.split_on_credits_end,
+                Split::TutorialSkipped => toggles.tutorial_skip,
+                Split::AbilityAcquired => self.ability_count_split > 0,
+                Split::Encounters(_) => toggles.split_on_encounter_start,
+                Split::PartyMemberJoined => toggles.split_on_party_join,
+                Split::Died => toggles.split_on_death,
+                Split::BossLowHP => toggles.boss_low_hp_split,
+                Split::FullHeal => toggles.split_on_full_heal,
+                Split::ItemCollected => toggles.split_on_item,
+                Split::WorldMap => toggles.split_world_map,
+                Split::AnyEncounterDone => toggles.split_all_encounters,
+                Split::BossPhase(_) => toggles.split_boss_hp_quarters,
             },
-            Action::Pause | Action::Resume => self.stop_when_loading,
-        })
+            Action::Pause | Action::Resume => toggles.stop_when_loading,
+            Action::SetGameTime(_) => toggles.use_play_time,
+        }
+    }
+
+    /// The number of splits in a run with these settings.
+    /// Splits that can repeat have no fixed number and are left out, see [`Split::repeats`].
+    pub fn enabled_split_count(&self) -> u8 {
+        let toggles = &self.toggles;
+        let experimental = toggles.experimental_features;
+        [
+            (toggles.mountain, 1),
+            (toggles.town, 1),
+            (toggles.mob, 1),
+            (toggles.level_up, 1),
+            (toggles.dungeon, 1),
+            // the boss always splits
+            (true, 1),
+            (toggles.split_on_credits_end, 1),
+            (toggles.tutorial_skip, 1),
+            (self.ability_count_split > 0, 1),
+            (toggles.boss_low_hp_split && experimental, 1),
+            (toggles.split_on_equipment_change, 1),
+            (toggles.split_on_item, 1),
+            (toggles.split_world_map, 1),
+            // three quarters, half, and a quarter
+            (toggles.split_boss_hp_quarters && experimental, 3),
+            // these can split any number of times
+            (toggles.split_on_encounter_start, 0),
+            (toggles.split_on_party_join, 0),
+            (toggles.split_on_death, 0),
+            (toggles.split_on_full_heal, 0),
+            (toggles.split_all_encounters && experimental, 0),
+        ]
+        .into_iter()
+        .filter(|&(enabled, _)| enabled)
+        .map(|(_, count)| count)
+        .sum()
     }
 }
@@ -0,0 +1,45 @@
+use core::fmt;
+
+use crate::progress::Action;
+
+const CAPACITY: usize = 32;
+
+/// A flight recorder for the state machine that also runs in release builds.
+/// Each entry is the state after `act` and the action that it returned.
+/// Repeated entries are only recorded once, so that the idle ticks
+/// do not push the interesting ones out.
+pub struct StateMachineDebugger {
+    results: [(u8, u8); CAPACITY],
+    head: u8,
+}
+
+impl StateMachineDebugger {
+    pub fn new() -> Self {
+        Self {
+            results: [(0, 0); CAPACITY],
+            head: 0,
+        }
+    }
+
+    pub fn record(&mut self, state: u8, action: Option<&Action>) {
+        let entry = (state, action.map_or(0, Action::discriminant));
+        let last = (usize::from(self.head) + CAPACITY - 1) % CAPACITY;
+        if self.results[last] == entry {
+            return;
+        }
+
+        self.results[usize::from(self.head)] = entry;
+        self.head = ((usize::from(self.head) + 1) % CAPACITY) as u8;
+    }
+}
+
+impl fmt::Display for StateMachineDebugger {
+    /// Oldest entries first, as `state:action`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (newer, older) = self.results.split_at(usize::from(self.head));
+        for (state, action) in older.iter().chain(newer) {
+            write!(f, "{state}:{action} ")?;
+        }
+        Ok(())
+    }
+}
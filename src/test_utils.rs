@@ -0,0 +1,430 @@
+use core::time::Duration;
+
+use arrayvec::{ArrayString, ArrayVec};
+use asr::{
+    watcher::{Pair, Watcher},
+    Address64,
+};
+use std::vec::Vec;
+
+use crate::{
+    data::{CombatPhase, EncounterHpSnapshot, GameDataSource},
+    progress::{Action, Progress, SplitLogic},
+    settings::{Settings, Toggles},
+};
+
+/// Game values that the tests set by hand, everything else can not be read.
+pub struct MockData {
+    pub play_time: Option<f64>,
+    pub is_loading: Option<bool>,
+    pub party_level: Option<u32>,
+    pub encounter_size: Option<u32>,
+    pub encounter_done: Option<bool>,
+    pub abilities: Option<u32>,
+    /// The first enemy of the encounter, together with its current HP
+    pub first_enemy: Option<(Address64, u32)>,
+    /// The other enemies of the encounter, together with their current HP
+    pub other_enemies: Vec<(Address64, u32)>,
+    party_level_watcher: Watcher<u32>,
+    encounter_size_watcher: Watcher<u32>,
+    encounter_done_watcher: Watcher<bool>,
+}
+
+impl MockData {
+    pub fn new() -> Self {
+        Self {
+            play_time: None,
+            is_loading: None,
+            party_level: None,
+            encounter_size: None,
+            encounter_done: None,
+            abilities: None,
+            first_enemy: None,
+            other_enemies: Vec::new(),
+            party_level_watcher: Watcher::new(),
+            encounter_size_watcher: Watcher::new(),
+            encounter_done_watcher: Watcher::new(),
+        }
+    }
+}
+
+impl GameDataSource for MockData {
+    fn play_time(&self) -> Option<u64> {
+        Some(self.play_time? as _)
+    }
+
+    fn play_time_f64(&self) -> Option<f64> {
+        self.play_time
+    }
+
+    fn party_level(&self) -> Option<u32> {
+        self.party_level
+    }
+
+    fn game_time_duration(&self) -> Option<Duration> {
+        Some(Duration::from_secs_f64(self.play_time?))
+    }
+
+    fn tutorial_completed(&self) -> Option<bool> {
+        None
+    }
+
+    fn total_death_count(&self) -> Option<u32> {
+        None
+    }
+
+    fn is_loading(&self) -> Option<bool> {
+        self.is_loading
+    }
+
+    fn is_paused(&self) -> Option<bool> {
+        None
+    }
+
+    fn is_cutscene(&self) -> Option<bool> {
+        None
+    }
+
+    fn is_credits_playing(&self) -> Option<bool> {
+        None
+    }
+
+    fn party_level_change(&mut self) -> Option<Pair<u32>> {
+        self.party_level_watcher.update(self.party_level).copied()
+    }
+
+    fn encounter_size(&mut self) -> Option<Pair<u32>> {
+        self.encounter_size_watcher
+            .update(self.encounter_size)
+            .copied()
+    }
+
+    fn in_encounter(&self) -> Option<bool> {
+        Some(self.encounter_size? > 0)
+    }
+
+    fn is_in_combat(&self) -> Option<bool> {
+        None
+    }
+
+    fn combat_phase(&self) -> Option<CombatPhase> {
+        None
+    }
+
+    fn is_encounter_new(&self) -> bool {
+        false
+    }
+
+    fn party_member_count(&self) -> Option<u32> {
+        None
+    }
+
+    fn is_party_full_hp(&self) -> Option<bool> {
+        None
+    }
+
+    fn encounter_done(&mut self) -> Option<Pair<bool>> {
+        self.encounter_done_watcher
+            .update(self.encounter_done)
+            .copied()
+    }
+
+    fn is_encounter_done(&self) -> Option<bool> {
+        self.encounter_done
+    }
+
+    fn encounter_wave_count(&self) -> Option<u32> {
+        None
+    }
+
+    fn all_enemies_defeated(&self) -> Option<bool> {
+        None
+    }
+
+    fn total_hp_remaining(&self) -> Option<u32> {
+        None
+    }
+
+    fn encounter_hp_snapshot(&self) -> Option<EncounterHpSnapshot> {
+        None
+    }
+
+    fn current_abilities_count(&self) -> Option<u32> {
+        self.abilities
+    }
+
+    fn current_equipment_count(&self) -> Option<u32> {
+        None
+    }
+
+    fn item_count(&self) -> Option<u32> {
+        None
+    }
+
+    fn is_world_map_unlocked(&self) -> Option<bool> {
+        None
+    }
+
+    fn current_save_slot(&self) -> Option<u32> {
+        None
+    }
+
+    fn first_enemy_start_hp(&self) -> Option<(Address64, u32)> {
+        self.first_enemy
+    }
+
+    fn first_enemy_id(&self) -> Option<u32> {
+        None
+    }
+
+    fn first_enemy_name(&self) -> Option<ArrayString<64>> {
+        None
+    }
+
+    fn current_hp(&self, enemy: Address64) -> Option<u32> {
+        self.first_enemy
+            .into_iter()
+            .chain(self.other_enemies.iter().copied())
+            .find_map(|(other, hp)| (other == enemy).then_some(hp))
+    }
+
+    fn enemy_max_hp(&self, _enemy: Address64) -> Option<u32> {
+        None
+    }
+
+    fn encounter_hp_phase(&self, _enemy: Address64, _thresholds: &[u32]) -> Option<u8> {
+        None
+    }
+
+    fn encounter_enemies(&self) -> Option<ArrayVec<Address64, 8>> {
+        None
+    }
+}
+
+/// The route splits, without any of the optional ones.
+pub fn route_settings() -> Settings {
+    Settings::with_toggles(Toggles {
+        mountain: true,
+        town: true,
+        mob: true,
+        level_up: true,
+        dungeon: true,
+        stop_when_loading: false,
+        split_on_credits_end: false,
+        show_debug_panel: false,
+        allow_mid_run_reset: true,
+        split_on_total_hp_zero: false,
+        tutorial_skip: false,
+        split_on_party_join: false,
+        split_on_death: false,
+        boss_low_hp_split: false,
+        invert_pause_behavior: false,
+        split_on_full_heal: false,
+        split_on_equipment_change: false,
+        split_on_item: false,
+        use_play_time: false,
+        verbose_logging: false,
+        persist_stats: false,
+        split_world_map: false,
+        split_all_encounters: false,
+        experimental_features: false,
+        enable_raw_reads: false,
+        practice_boss: false,
+        split_on_encounter_start: false,
+        start_on_first_load: false,
+        start_on_first_combat: false,
+        start_manually: false,
+        split_boss_hp_quarters: false,
+        split_on_first_ability: false,
+    })
+}
+
+/// A synthetic demo run from the new game to the boss, one [`GameSnapshot`] per tick.
+/// It has been written by hand, not recorded from the game, and only contains the ticks
+/// at which something changes. It has to be updated together with the split behavior,
+/// the fixture is then written again by `write_the_synthetic_demo_run_fixture`.
+#[rustfmt::skip]
+pub const SYNTHETIC_DEMO_RUN: [GameSnapshot; 17] = [
+    //                ticks  load   done   level  size  enemy   hp
+    // The title screen still shows the play time of the last save
+    GameSnapshot::at(7200.0, false, false, 3,     0,    0,      0),
+    GameSnapshot::at(   1.0, false, false, 3,     0,    0,      0),
+    // The tutorial, the mountain, the town, and the dungeon
+    GameSnapshot::at(   2.0, true,  false, 3,     0,    0,      0),
+    GameSnapshot::at(   3.0, false, false, 3,     0,    0,      0),
+    GameSnapshot::at(   4.0, true,  false, 3,     0,    0,      0),
+    GameSnapshot::at(   5.0, false, false, 3,     0,    0,      0),
+    GameSnapshot::at(   6.0, true,  false, 3,     0,    0,      0),
+    GameSnapshot::at(   7.0, false, false, 3,     0,    0,      0),
+    GameSnapshot::at(   8.0, true,  false, 3,     0,    0,      0),
+    GameSnapshot::at(   9.0, false, false, 3,     0,    0,      0),
+    // The mob
+    GameSnapshot::at(  10.0, false, false, 3,     4,    0,      0),
+    GameSnapshot::at(  11.0, false, true,  3,     4,    0,      0),
+    // The level up
+    GameSnapshot::at(  12.0, false, false, 3,     0,    0,      0),
+    GameSnapshot::at(  13.0, false, false, 4,     0,    0,      0),
+    // The boss
+    GameSnapshot::at(  14.0, false, false, 4,     0,    0x1000, 700),
+    GameSnapshot::at(  15.0, false, false, 4,     0,    0x1000, 350),
+    GameSnapshot::at(  16.0, false, false, 4,     0,    0x1000, 0),
+];
+
+/// [`SYNTHETIC_DEMO_RUN`] as it is replayed by the [`FixturePlayer`].
+pub const SYNTHETIC_DEMO_RUN_FIXTURE: &[u8] = include_bytes!("../fixtures/demo_run.bin");
+
+/// The game values of a single tick.
+#[derive(Copy, Clone, Debug)]
+pub struct GameSnapshot {
+    pub play_time: f64,
+    pub is_loading: bool,
+    pub encounter_done: bool,
+    pub party_level: u8,
+    pub encounter_size: u8,
+    /// The first enemy, with 0 for no enemy
+    pub first_enemy: u64,
+    pub first_enemy_hp: u32,
+}
+
+impl GameSnapshot {
+    /// Little endian play time, flags (loading, encounter done), party level,
+    /// encounter size, first enemy address, and first enemy HP.
+    pub const SIZE: usize = 23;
+
+    /// The play time is given in ticks of a 60 fps game.
+    pub const fn at(
+        ticks: f64,
+        is_loading: bool,
+        encounter_done: bool,
+        party_level: u8,
+        encounter_size: u8,
+        first_enemy: u64,
+        first_enemy_hp: u32,
+    ) -> Self {
+        Self {
+            play_time: ticks / 60.0,
+            is_loading,
+            encounter_done,
+            party_level,
+            encounter_size,
+            first_enemy,
+            first_enemy_hp,
+        }
+    }
+
+    pub fn encode(&self) -> [u8; Self::SIZE] {
+        let flags = u8::from(self.is_loading) | u8::from(self.encounter_done) << 1;
+        let mut bytes = [0; Self::SIZE];
+        bytes[..8].copy_from_slice(&self.play_time.to_le_bytes());
+        bytes[8..11].copy_from_slice(&[flags, self.party_level, self.encounter_size]);
+        bytes[11..19].copy_from_slice(&self.first_enemy.to_le_bytes());
+        bytes[19..].copy_from_slice(&self.first_enemy_hp.to_le_bytes());
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8; Self::SIZE]) -> Self {
+        let (play_time, rest) = bytes.split_at(8);
+        let (&[flags, party_level, encounter_size], rest) = rest.split_first_chunk().unwrap();
+        let (first_enemy, first_enemy_hp) = rest.split_at(8);
+        Self {
+            play_time: f64::from_le_bytes(play_time.try_into().unwrap()),
+            is_loading: flags & 1 != 0,
+            encounter_done: flags & 2 != 0,
+            party_level,
+            encounter_size,
+            first_enemy: u64::from_le_bytes(first_enemy.try_into().unwrap()),
+            first_enemy_hp: u32::from_le_bytes(first_enemy_hp.try_into().unwrap()),
+        }
+    }
+
+    fn apply(self, data: &mut MockData) {
+        data.play_time = Some(self.play_time);
+        data.is_loading = Some(self.is_loading);
+        data.encounter_done = Some(self.encounter_done);
+        data.party_level = Some(self.party_level.into());
+        data.encounter_size = Some(self.encounter_size.into());
+        data.first_enemy = (self.first_enemy != 0)
+            .then(|| (Address64::new(self.first_enemy), self.first_enemy_hp));
+    }
+}
+
+/// Replays a fixture of [`GameSnapshot`]s through a [`Progress`].
+pub struct FixturePlayer {
+    snapshots: ArrayVec<GameSnapshot, 64>,
+}
+
+impl FixturePlayer {
+    pub fn new(fixture: &[u8]) -> Self {
+        let (snapshots, rest) = fixture.as_chunks::<{ GameSnapshot::SIZE }>();
+        assert!(
+            rest.is_empty(),
+            "the fixture ends in the middle of a snapshot"
+        );
+        Self {
+            snapshots: snapshots.iter().map(GameSnapshot::decode).collect(),
+        }
+    }
+
+    /// Runs one tick per snapshot and returns the actions of all of them.
+    pub fn play<S: SplitLogic>(
+        &self,
+        progress: &mut Progress<S>,
+        settings: &Settings,
+    ) -> Vec<Action> {
+        let mut data = MockData::new();
+        let mut actions = Vec::new();
+        for snapshot in &self.snapshots {
+            snapshot.apply(&mut data);
+            actions.extend(run_tick(progress, &mut data, settings));
+        }
+        actions
+    }
+}
+
+/// Runs one tick like the main loop does and returns the actions that the settings allow.
+pub fn run_tick<S: SplitLogic>(
+    progress: &mut Progress<S>,
+    data: &mut MockData,
+    settings: &Settings,
+) -> Vec<Action> {
+    progress.tick();
+    let mut actions = Vec::new();
+    loop {
+        let (action, _) = progress.act_diag(data, settings);
+        let Some(action) = action else {
+            return actions;
+        };
+        if let Some(action) = settings.filter_with_context(action, progress.current_state_name()) {
+            actions.push(action);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use super::*;
+
+    fn encode_synthetic_demo_run() -> Vec<u8> {
+        SYNTHETIC_DEMO_RUN
+            .iter()
+            .flat_map(GameSnapshot::encode)
+            .collect()
+    }
+
+    #[test]
+    fn the_fixture_matches_the_synthetic_demo_run() {
+        assert!(
+            encode_synthetic_demo_run() == SYNTHETIC_DEMO_RUN_FIXTURE,
+            "the fixture is out of date, run `cargo test -- --ignored write_the_synthetic_demo_run_fixture`"
+        );
+    }
+
+    #[test]
+    #[ignore = "writes the fixture"]
+    fn write_the_synthetic_demo_run_fixture() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/demo_run.bin");
+        fs::write(path, encode_synthetic_demo_run()).unwrap();
+    }
+}